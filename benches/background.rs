@@ -0,0 +1,79 @@
+//! Compares the old x-only sweep and prune with the grid in `Background::calculate_collisions`,
+//! by the pairs they find and the time of a whole frame of `update` and drawing, and drawing a
+//! frame with a shape per point and link against a single mesh.
+//!
+//! Run with `cargo bench --features bench`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use exceptis_portfolio::{Background, BoundaryMode, NeighborSearch};
+
+const FRAMES: u32 = 20;
+
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        f();
+    }
+    start.elapsed() / FRAMES
+}
+
+fn main() {
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(1920.0, 1080.0));
+    let ctx = egui::Context::default();
+    let raw_input = egui::RawInput {
+        screen_rect: Some(screen),
+        ..Default::default()
+    };
+
+    println!(
+        "{:>6} | {:>11} {:>11} | {:>11} {:>10} | {:>12} {:>10}",
+        "points",
+        "sweep pairs",
+        "sweep frame",
+        "grid pairs",
+        "grid frame",
+        "shapes frame",
+        "mesh frame"
+    );
+    for count in [100, 1_000, 10_000] {
+        let mut background = Background::with_seed(0);
+        // The sweep doesn't know about links across the edges.
+        background.config.boundary = BoundaryMode::Reflect;
+        background.fill(screen, count);
+
+        // A step of the simulation and building what to draw, without egui's tessellation.
+        let mut search = |search: NeighborSearch| {
+            background.set_neighbor_search(search);
+            background.calculate_collisions();
+            let pairs = background.link_count();
+            let frame_time = time(|| {
+                let _ = ctx.run(raw_input.clone(), |ctx| {
+                    background.update(1.0 / 60.0, &[], screen);
+                    background.render_draw_data(ctx.layer_painter(egui::LayerId::background()));
+                });
+            });
+            (pairs, frame_time)
+        };
+        let (sweep_pairs, sweep_time) = search(NeighborSearch::SweepX);
+        let (grid_pairs, grid_time) = search(NeighborSearch::Grid);
+
+        // Tessellating is where the shapes cost the most.
+        let mut frame_time = |batch_mesh: bool| {
+            background.config.batch_mesh = batch_mesh;
+            time(|| {
+                let output = ctx.run(raw_input.clone(), |ctx| {
+                    background.update(1.0 / 60.0, &[], screen);
+                    background.render_draw_data(ctx.layer_painter(egui::LayerId::background()));
+                });
+                black_box(ctx.tessellate(output.shapes, output.pixels_per_point));
+            })
+        };
+        let (shapes_time, mesh_time) = (frame_time(false), frame_time(true));
+
+        println!(
+            "{count:>6} | {sweep_pairs:>11} {sweep_time:>11.2?} | {grid_pairs:>11} {grid_time:>10.2?} | {shapes_time:>12.2?} {mesh_time:>10.2?}"
+        );
+    }
+}
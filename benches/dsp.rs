@@ -0,0 +1,56 @@
+//! Times the FFT of `dsp` at power of two and mixed-radix lengths against a plain DFT, and
+//! a biquad filtering a second of audio.
+//!
+//! Run with `cargo bench --bench dsp`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use exceptis_portfolio::dsp::{self, Biquad, Complex};
+
+const RUNS: u32 = 20;
+
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        f();
+    }
+    start.elapsed() / RUNS
+}
+
+/// Something to transform, the values don't change the timing.
+fn signal(n: usize) -> Vec<Complex> {
+    (0..n)
+        .map(|i| Complex::new((i as f32 * 0.1).sin(), (i as f32 * 0.37).cos()))
+        .collect()
+}
+
+fn main() {
+    println!("{:>6} | {:>12} | {:>12}", "length", "fft", "naive dft");
+    // Powers of two, products of small primes, and a prime.
+    for n in [256, 1_000, 1_024, 4_096, 4_800, 65_536, 1_009] {
+        let input = signal(n);
+        let mut data = input.clone();
+        let fft = time(|| {
+            data.copy_from_slice(&input);
+            dsp::fft(black_box(&mut data));
+        });
+        // The DFT takes minutes beyond a few thousand samples.
+        let naive = if n <= 4_800 {
+            format!(
+                "{:?}",
+                time(|| {
+                    black_box(dsp::naive_dft(black_box(&input)));
+                })
+            )
+        } else {
+            "–".to_owned()
+        };
+        println!("{n:>6} | {:>12} | {naive:>12}", format!("{fft:?}"));
+    }
+
+    let mut samples: Vec<f32> = (0..48_000).map(|i| (i as f32 * 0.05).sin()).collect();
+    let mut filter = Biquad::low_pass(48_000.0, 1_000.0, std::f32::consts::FRAC_1_SQRT_2);
+    let biquad = time(|| filter.process_block(black_box(&mut samples)));
+    println!("\nbiquad, 1 s at 48 kHz | {biquad:?}");
+}
@@ -2,6 +2,8 @@
 # This scripts runs various CI-like checks in a convenient way.
 set -eux
 
+# --all-features includes `native-audio`, which needs the ALSA headers on Linux (libasound2-dev).
+
 cargo check --quiet --workspace --all-targets
 cargo check --quiet --workspace --all-features --lib --target wasm32-unknown-unknown
 cargo fmt --all -- --check
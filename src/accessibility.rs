@@ -0,0 +1,182 @@
+//! Preferences for visitors sensitive to motion or in need of more contrast.
+//!
+//! On the web they follow the `prefers-reduced-motion` and `prefers-contrast` media queries
+//! until changed in the settings, natively the settings are the only source.
+
+use eframe::egui;
+
+/// How much the background moves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Motion {
+    #[default]
+    Full,
+    Slow,
+    Still,
+}
+
+impl Motion {
+    pub const ALL: [Self; 3] = [Self::Full, Self::Slow, Self::Still];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Full => "Full",
+            Self::Slow => "Slow",
+            Self::Still => "Still",
+        }
+    }
+
+    /// How fast the background runs compared to real time, `None` if it stands still.
+    pub fn speed(self) -> Option<f32> {
+        match self {
+            Self::Full => Some(1.0),
+            Self::Slow => Some(0.25),
+            Self::Still => None,
+        }
+    }
+}
+
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Accessibility {
+    /// `None` follows the system.
+    pub motion: Option<Motion>,
+
+    /// `None` follows the system.
+    pub high_contrast: Option<bool>,
+}
+
+impl Accessibility {
+    pub fn motion(&self) -> Motion {
+        self.motion.unwrap_or_else(|| {
+            if system_prefers("(prefers-reduced-motion: reduce)") {
+                Motion::Still
+            } else {
+                Motion::Full
+            }
+        })
+    }
+
+    pub fn high_contrast(&self) -> bool {
+        self.high_contrast
+            .unwrap_or_else(|| system_prefers("(prefers-contrast: more)"))
+    }
+
+    /// Make the visuals of `ctx` the plain dark or light ones, with stronger text and borders
+    /// when [`Self::high_contrast`] is on.
+    pub fn apply_visuals(&self, ctx: &egui::Context) {
+        let mut visuals = if ctx.style().visuals.dark_mode {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        };
+        if self.high_contrast() {
+            let strong = visuals.strong_text_color();
+            visuals.override_text_color = Some(strong);
+            visuals.window_stroke = egui::Stroke::new(2.0, strong);
+            for widget in [
+                &mut visuals.widgets.noninteractive,
+                &mut visuals.widgets.inactive,
+            ] {
+                widget.bg_stroke = egui::Stroke::new(1.0, strong);
+            }
+        }
+        if ctx.style().visuals != visuals {
+            ctx.set_visuals(visuals);
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let system = |value: &str| format!("System ({value})");
+
+        egui::Grid::new("accessibility")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Motion");
+                egui::ComboBox::from_id_source("motion")
+                    .selected_text(match self.motion {
+                        Some(motion) => motion.name().to_owned(),
+                        None => system(self.motion().name()),
+                    })
+                    .show_ui(ui, |ui| {
+                        let current = Self::default().motion();
+                        ui.selectable_value(&mut self.motion, None, system(current.name()));
+                        for motion in Motion::ALL {
+                            ui.selectable_value(&mut self.motion, Some(motion), motion.name());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("High contrast");
+                let on_off = |on: bool| if on { "On" } else { "Off" };
+                egui::ComboBox::from_id_source("high_contrast")
+                    .selected_text(match self.high_contrast {
+                        Some(on) => on_off(on).to_owned(),
+                        None => system(on_off(self.high_contrast())),
+                    })
+                    .show_ui(ui, |ui| {
+                        let current = Self::default().high_contrast();
+                        ui.selectable_value(&mut self.high_contrast, None, system(on_off(current)));
+                        for on in [false, true] {
+                            ui.selectable_value(&mut self.high_contrast, Some(on), on_off(on));
+                        }
+                    });
+                ui.end_row();
+            });
+    }
+}
+
+/// Whether a media query matches, always `false` natively.
+///
+/// This is asked several times every frame, so every query is only handed to the browser once
+/// and its list just tells whether it matches from then on.
+fn system_prefers(query: &'static str) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        _ = query;
+        false
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        type Lists = std::collections::HashMap<&'static str, Option<web_sys::MediaQueryList>>;
+        thread_local! {
+            static LISTS: std::cell::RefCell<Lists> = Default::default();
+        }
+        LISTS.with(|lists| {
+            lists
+                .borrow_mut()
+                .entry(query)
+                .or_insert_with(|| {
+                    web_sys::window().and_then(|window| window.match_media(query).ok().flatten())
+                })
+                .as_ref()
+                .map_or(false, |list| list.matches())
+        })
+    }
+}
+
+#[test]
+fn test_visuals_follow_high_contrast() {
+    let ctx = egui::Context::default();
+    let mut accessibility = Accessibility {
+        high_contrast: Some(true),
+        ..Default::default()
+    };
+    accessibility.apply_visuals(&ctx);
+    let visuals = ctx.style().visuals.clone();
+    assert!(visuals.dark_mode);
+    assert_eq!(
+        visuals.override_text_color,
+        Some(visuals.strong_text_color())
+    );
+
+    // Switching it off again goes back to the plain visuals, in light mode too.
+    ctx.set_visuals(egui::Visuals::light());
+    accessibility.high_contrast = Some(false);
+    accessibility.apply_visuals(&ctx);
+    assert!(ctx.style().visuals == egui::Visuals::light());
+
+    // Natively there is no system preference to follow.
+    assert_eq!(Accessibility::default().motion(), Motion::Full);
+    assert_eq!(Motion::Still.speed(), None);
+}
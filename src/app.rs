@@ -1,32 +1,19 @@
 use eframe::egui;
 
+use crate::accessibility::Accessibility;
+use crate::attractor::AttractorWindow;
+use crate::audio_reactive::AudioReactive;
+use crate::background::Background;
+use crate::chaos::ChaosViewer;
+use crate::chart::ChartWindow;
+use crate::contact::ContactWindow;
+use crate::content::{self, ContentSource, TextContents};
+use crate::content_source;
 use crate::easy_mark_viewer::easy_mark;
-use rand::prelude::*;
-use rand_chacha::ChaCha20Rng;
-
-// Define structs that match the TOML structure
-#[derive(serde::Deserialize)]
-struct TextContents {
-    work_experience: WorkExperience,
-    biography: TextBlock,
-    about_page: TextBlock,
-}
-
-#[derive(serde::Deserialize)]
-struct TextBlock {
-    text: String,
-}
-
-#[derive(serde::Deserialize)]
-struct WorkExperience {
-    lucid_software: ExperienceDetails,
-    freelance_projects: ExperienceDetails,
-}
-
-#[derive(serde::Deserialize)]
-struct ExperienceDetails {
-    description: String,
-}
+use crate::editor::{self, ContentEditor};
+use crate::pointer::PointerTracker;
+use crate::quality::Quality;
+use crate::rain::RainWindow;
 
 /// We derive Deserialize/Serialize, so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -39,36 +26,154 @@ pub struct PortfolioApp {
     parsed_text: TextContents,
 
     #[serde(skip)]
+    content_source: Box<dyn ContentSource>,
+
+    /// Why the last content reload failed, shown in a window until dismissed.
+    #[serde(skip)]
+    content_error: Option<String>,
+
+    /// Whether [`Self::parsed_text`] came from the content source, rather than being the
+    /// embedded fallback for a source that never loaded.
+    #[serde(skip)]
+    contents_loaded: bool,
+
+    #[serde(skip)]
+    editor: ContentEditor,
+
+    #[serde(skip)]
+    contact: ContactWindow,
+
+    #[serde(skip)]
+    rain: RainWindow,
+
+    /// Persisted for its favorites.
+    chaos: ChaosViewer,
+
+    attractors: AttractorWindow,
+
+    market: ChartWindow,
+
     background: Background,
 
+    #[serde(skip)]
+    pointers: PointerTracker,
+
+    quality: Quality,
+
+    accessibility: Accessibility,
+
+    audio_reactive: AudioReactive,
+
     about_page: bool,
+
+    background_settings: bool,
+
+    /// Code of the [`content::Locale`] the text contents are shown in.
+    locale: String,
 }
 
 impl Default for PortfolioApp {
     fn default() -> Self {
-        let text_contents: TextContents =
-            toml::from_str(include_str!("../assets/text_contents.toml"))
-                .expect("Failed to parse TOML");
-        Self {
+        let mut app = Self {
             // Example stuff:
             label: "Hello World!".to_owned(),
-            parsed_text: text_contents,
+            parsed_text: TextContents::default(),
+            content_source: content_source::default_source(),
+            content_error: None,
+            contents_loaded: false,
+            editor: ContentEditor::default(),
+            contact: ContactWindow::default(),
+            rain: RainWindow::default(),
+            chaos: ChaosViewer::default(),
+            attractors: AttractorWindow::default(),
+            market: ChartWindow::default(),
             background: Background::default(),
+            pointers: PointerTracker::default(),
+            quality: Quality::default(),
+            accessibility: Accessibility::default(),
+            audio_reactive: AudioReactive::default(),
             about_page: true,
-        }
+            background_settings: false,
+            locale: content::DEFAULT_LOCALE.to_owned(),
+        };
+        app.reload_contents();
+        app
     }
 }
 
 impl PortfolioApp {
     /// Called once before the first frame.
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        Default::default()
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let app = Self::restore_or_default(cc);
+        log::info!("Background seed: {}", app.background.seed());
+        app
+    }
+
+    fn restore_or_default(cc: &eframe::CreationContext<'_>) -> Self {
+        // Load previous app state (if any).
+        if let Some(mut app) = cc
+            .storage
+            .and_then(|storage| eframe::get_value::<Self>(storage, eframe::APP_KEY))
+        {
+            let locale = std::mem::take(&mut app.locale);
+            app.set_locale(&locale);
+            return app;
+        }
+
+        // First visit, pick the language of the browser if we have it.
+        #[cfg(target_arch = "wasm32")]
+        let locale = content::browser_locale().unwrap_or(content::DEFAULT_LOCALE);
+        #[cfg(not(target_arch = "wasm32"))]
+        let locale = content::DEFAULT_LOCALE;
+
+        let mut app = Self::default();
+        app.set_locale(locale);
+        app
+    }
+
+    /// Switch the text contents to `locale`, unknown locales fall back to the default locale.
+    fn set_locale(&mut self, locale: &str) {
+        let locale = content::find_locale(locale).map_or(content::DEFAULT_LOCALE, |l| l.code);
+        if self.locale != locale {
+            self.editor.discard_edits("the language changed");
+            self.locale = locale.to_owned();
+            self.reload_contents();
+        }
+    }
+
+    /// Load the text contents for the current locale, keeping the old contents if that fails.
+    ///
+    /// Without old contents, e.g. a broken file at startup, the copy compiled into the binary
+    /// is shown.
+    fn reload_contents(&mut self) {
+        match TextContents::load(self.content_source.as_mut(), &self.locale) {
+            Ok(contents) => {
+                self.editor.discard_edits("the contents were reloaded");
+                self.parsed_text = contents;
+                self.content_error = None;
+                self.contents_loaded = true;
+            }
+            Err(err) => {
+                log::error!("Failed to load text contents: {err}");
+                if !self.contents_loaded {
+                    self.editor.discard_edits("the contents were reloaded");
+                    self.parsed_text =
+                        TextContents::load(&mut content_source::EmbeddedSource, &self.locale)
+                            .unwrap_or_default();
+                }
+                self.content_error = Some(err.to_string());
+            }
+        }
     }
 }
 
 impl eframe::App for PortfolioApp {
     /// Called each time the UI needs repainting, which may be many times per second.
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if self.content_source.changed() {
+            self.reload_contents();
+        }
+
         let screen_size = ctx.screen_rect();
         let text_window_max_width: f32 = screen_size.width() - screen_size.width() * 0.2;
 
@@ -76,17 +181,42 @@ impl eframe::App for PortfolioApp {
             self.background.add_points(screen_size)
         }
 
-        let input = ctx.input(|input| input.clone());
-        self.background.update_points(
-            ctx.pointer_latest_pos().unwrap_or(egui::Pos2::ZERO),
-            screen_size,
-            &input.unstable_dt,
+        // The windows as they were laid out last frame, this frame's aren't shown yet.
+        self.background.set_obstacles(ctx.memory(|memory| {
+            memory
+                .layer_ids()
+                .filter(|layer| {
+                    layer.order == egui::Order::Middle && memory.areas().is_visible(layer)
+                })
+                .filter_map(|layer| memory.area_rect(layer.id))
+                .collect::<Vec<_>>()
+        }));
+        self.accessibility.apply_visuals(ctx);
+        self.background.set_contrast_color(
+            self.accessibility
+                .high_contrast()
+                .then(|| ctx.style().visuals.strong_text_color()),
         );
 
-        self.background.calculate_collisions();
+        self.quality.record_frame_time(frame.info().cpu_usage);
+        self.background.set_quality(self.quality.scale());
+
+        let pointers = self.pointers.pointers(ctx);
+        let simulation_dt = self.quality.simulation_dt(ctx, self.accessibility.motion());
+        self.background
+            .set_audio(simulation_dt.and_then(|dt| self.audio_reactive.update(dt)));
+        match simulation_dt {
+            Some(dt) => self.background.update(dt, &pointers, screen_size),
+            // Standing still, but still following the size of the window.
+            None => self.background.step(0, &[], screen_size),
+        }
 
+        self.background.record_frame_time(frame.info().cpu_usage);
         let painter = ctx.layer_painter(egui::LayerId::background());
         self.background.render_draw_data(painter);
+        if self.background.config.show_frame_times {
+            self.background.frame_time_overlay(ctx);
+        }
 
         let clear_frame = egui::Frame {
             fill: egui::Color32::from_rgba_premultiplied(0, 0, 0, 0),
@@ -107,6 +237,57 @@ impl eframe::App for PortfolioApp {
                     if ui.selectable_label(self.about_page, "About").clicked() {
                         self.about_page = !self.about_page;
                     }
+
+                    if ui.selectable_label(self.contact.open, "Contact").clicked() {
+                        self.contact.open = !self.contact.open;
+                    }
+
+                    if ui.selectable_label(self.rain.open, "Rain").clicked() {
+                        if self.rain.open {
+                            self.rain.close(&mut self.background);
+                        } else {
+                            self.rain.open = true;
+                        }
+                    }
+
+                    if ui.selectable_label(self.chaos.open, "Chaos").clicked() {
+                        self.chaos.open = !self.chaos.open;
+                    }
+
+                    if ui
+                        .selectable_label(self.attractors.open, "Attractors")
+                        .clicked()
+                    {
+                        self.attractors.open = !self.attractors.open;
+                    }
+
+                    if ui.selectable_label(self.market.open, "Market").clicked() {
+                        self.market.open = !self.market.open;
+                    }
+
+                    if ui
+                        .selectable_label(self.background_settings, "Settings")
+                        .clicked()
+                    {
+                        self.background_settings = !self.background_settings;
+                    }
+
+                    let mut locale = self.locale.clone();
+                    let selected = content::find_locale(&locale).map_or("", |l| l.name);
+                    egui::ComboBox::from_id_source("language")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            for l in content::LOCALES {
+                                ui.selectable_value(&mut locale, l.code.to_owned(), l.name);
+                            }
+                        });
+                    self.set_locale(&locale);
+
+                    if editor::available()
+                        && ui.selectable_label(self.editor.open, "Edit").clicked()
+                    {
+                        self.editor.open = !self.editor.open;
+                    }
                 });
             });
 
@@ -137,263 +318,142 @@ impl eframe::App for PortfolioApp {
                 });
             });
 
-        // draw in continuous mode.
-        ctx.request_repaint();
-    }
-
-    /// Called by the framework to save state before shutdown.
-    fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
-    }
-}
-
-const PT_LINE_DISTANCE: f32 = 120.0;
-
-#[derive(Copy, Clone, Debug)]
-struct Point {
-    x: f32,
-    y: f32,
-    xv: f32,
-    yv: f32,
-}
-
-impl Point {
-    // Normalize the point's velocity
-    fn distance(p1: &Point, p2: &Point) -> f32 {
-        ((p2.x - p1.x).powi(2) + (p2.y - p1.y).powi(2)).sqrt()
-    }
-    // Function to calculate the unit vector from one point to another
-    fn direction_to(&self, other: &Point) -> Point {
-        let dx = other.x - self.x;
-        let dy = other.y - self.y;
-        let dist = Point::distance(self, other);
-        Point {
-            x: dx / dist,
-            y: dy / dist,
-            xv: 0.0,
-            yv: 0.0,
+        if self.background_settings {
+            let mut config = self.background.config.clone();
+            egui::Window::new("Settings")
+                .open(&mut self.background_settings)
+                .auto_sized()
+                .show(ctx, |ui| {
+                    config.ui(ui);
+                    ui.separator();
+                    self.audio_reactive.ui(ui);
+                    ui.separator();
+                    self.accessibility.ui(ui);
+                    ui.separator();
+                    self.quality.ui(ui);
+                });
+            if config != self.background.config {
+                self.background.set_config(config);
+            }
         }
-    }
-
-    // Elastic collision logic, assuming equal mass for simplicity
-    fn elastic_collision(p1: &mut Point, p2: &mut Point) {
-        let unit_normal = p1.direction_to(p2);
-        let unit_tangent = Point {
-            x: -unit_normal.y,
-            y: unit_normal.x,
-            xv: 0.0,
-            yv: 0.0,
-        };
-
-        // Decompose velocities into normal and tangent components
-        let v1n = unit_normal.x * p1.xv + unit_normal.y * p1.yv;
-        let v1t = unit_tangent.x * p1.xv + unit_tangent.y * p1.yv;
-
-        let v2n = unit_normal.x * p2.xv + unit_normal.y * p2.yv;
-        let v2t = unit_tangent.x * p2.xv + unit_tangent.y * p2.yv;
-
-        // For elastic collisions, the velocities along the normal direction exchange
-        // since we assume equal mass, the formula simplifies
-        let v1n_final = v2n;
-        let v2n_final = v1n;
 
-        // Update velocities, the tangent component remains unchanged
-        p1.xv = v1n_final * unit_normal.x + v1t * unit_tangent.x;
-        p1.yv = v1n_final * unit_normal.y + v1t * unit_tangent.y;
-
-        p2.xv = v2n_final * unit_normal.x + v2t * unit_tangent.x;
-        p2.yv = v2n_final * unit_normal.y + v2t * unit_tangent.y;
-    }
-}
-
-// Implementing the Sub trait to overload the '-' operator for Point
-impl<'a, 'b> std::ops::Sub<&'b Point> for &'a Point {
-    type Output = Point;
-
-    fn sub(self, other: &'b Point) -> Point {
-        Point {
-            x: self.x - other.x,
-            y: self.y - other.y,
-            xv: self.xv - other.xv,
-            yv: self.yv - other.yv,
+        if self.contact.open {
+            self.contact.show(ctx, &self.parsed_text.contact);
         }
-    }
-}
-
-struct Background {
-    points: Vec<Point>,
-    collisions: Vec<(usize, usize)>, // Stores tuples of indices of colliding points
-}
 
-impl Background {
-    fn default() -> Self {
-        Self {
-            points: Vec::new(),
-            collisions: Vec::new(),
+        if self.rain.open {
+            self.rain.show(ctx, &mut self.background);
         }
-    }
-
-    fn has_points(&self) -> bool {
-        !self.points.is_empty()
-    }
-
-    fn add_points(&mut self, screen_size: egui::Rect) {
-        let mut new_points: Vec<Point> = Vec::new();
-
-        let mut rng = ChaCha20Rng::from_entropy();
-
-        let num_points: i32 = ((screen_size.width() / 3.0) * 0.5) as i32;
 
-        for _ in 0..num_points {
-            new_points.push(Point {
-                x: rng.gen_range(0.0..screen_size.width()),
-                y: rng.gen_range(0.0..screen_size.height()),
-                xv: rng.gen_range(-20.0..=20.0),
-                yv: rng.gen_range(-20.0..=20.0),
-            });
+        if self.chaos.open {
+            self.chaos.show(ctx);
         }
-        self.points = new_points;
-    }
-
-    fn update_points(&mut self, mouse_pos: egui::Pos2, screen_size: egui::Rect, dt: &f32) {
-        for point in &mut self.points {
-            // Update point velocity and position
-
-            point.x += point.xv * dt * 10.;
-            point.y += point.yv * dt * 10.;
 
-            //point.xv += 9.8 * dt * 10.;
-            point.yv += 0.98 * dt * 10.;
-
-            // Repel from mouse cursor
-            let distance_to_mouse = (egui::Pos2::new(point.x, point.y) - mouse_pos).length();
-            if distance_to_mouse < 60.0 {
-                let direction = (egui::Pos2::new(point.x, point.y) - mouse_pos).normalized();
-                point.xv += direction.x * 30.;
-                point.yv += direction.y * 30.;
-            }
+        if self.attractors.open {
+            self.attractors.show(ctx);
+        }
 
-            point.xv *= 0.999;
-            point.yv *= 0.999;
+        if self.market.open {
+            self.market.show(ctx);
+        }
 
-            // Wrap around screen edges
-            point.x = point.x.rem_euclid(screen_size.width());
-            point.y = point.y.rem_euclid(screen_size.height());
+        if self.editor.open {
+            self.editor.show(
+                ctx,
+                &mut self.parsed_text,
+                self.content_source.as_mut(),
+                &self.locale,
+            );
         }
-    }
 
-    // Sweep and Prune algorithm implemented based on (https://youtu.be/eED4bSkYCB8?si=ljSOo6s2a9xyHzkI&t=941)
-    fn calculate_collisions(&mut self) {
-        self.collisions.clear();
-        let mut active_intervals: Vec<usize> = Vec::new();
-
-        // Sort points by their x-coordinate for efficient collision detection
-        let mut sorted_points: Vec<usize> = (0..self.points.len()).collect();
-        sorted_points.sort_by_key(|&i| self.points[i].x as i32);
-
-        for &i in &sorted_points {
-            let point = &self.points[i];
-
-            // Remove points from active intervals that are too far behind the current point
-            active_intervals.retain(|&j| self.points[j].x + PT_LINE_DISTANCE >= point.x);
-
-            // Check for collisions within the active intervals
-            for &j in &active_intervals {
-                // Ensure we only check collisions for distinct points
-                if j != i {
-                    let distance = (self.points[j].x - point.x).abs();
-                    if distance <= PT_LINE_DISTANCE {
-                        // Since we are dealing with 1D intervals, a simple distance check is sufficient
-                        // For 2D or 3D, you'd need more complex overlap checks
-                        self.collisions.push((j, i));
-                    }
-                }
+        if let Some(err) = &self.content_error {
+            let mut open = true;
+            egui::Window::new("Content error")
+                .open(&mut open)
+                .auto_sized()
+                .show(ctx, |ui| {
+                    ui.label(
+                        "The text contents could not be loaded, showing the last good version.",
+                    );
+                    ui.monospace(err);
+                });
+            if !open {
+                self.content_error = None;
             }
+        }
 
-            // Add the current point to active intervals
-            active_intervals.push(i);
+        // Animate only while the background moves, input wakes us up otherwise. Content files
+        // edited in another window still have to be noticed while we are paused.
+        if simulation_dt.is_some() {
+            ctx.request_repaint();
+        } else if let Some(interval) = self.content_source.poll_interval() {
+            ctx.request_repaint_after(interval);
         }
     }
 
-    fn get_two_mut(slice: &mut [Point], idx1: usize, idx2: usize) -> (&mut Point, &mut Point) {
-        assert!(idx1 != idx2, "Indices must be distinct");
-        let (first, second) = if idx1 < idx2 {
-            (idx1, idx2)
-        } else {
-            (idx2, idx1)
-        };
-        let (head, tail) = slice.split_at_mut(second);
-        (&mut head[first], &mut tail[0])
+    /// Called by the framework to save state before shutdown.
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        // The rain only lasts while its window is open, save the background as it was before.
+        let rain = self
+            .rain
+            .config_before()
+            .map(|before| std::mem::replace(&mut self.background.config, before.clone()));
+        eframe::set_value(storage, eframe::APP_KEY, self);
+        if let Some(rain) = rain {
+            self.background.config = rain;
+        }
     }
+}
 
-    // Returns data for egui to draw, not drawing directly
-    fn prepare_draw_data(&mut self) -> Vec<DrawCommand> {
-        let mut commands = Vec::new();
-
-        for &point in &self.points {
-            commands.push(DrawCommand::Circle {
-                center: egui::Pos2::new(point.x, point.y),
-                radius: 5.0,
-                color: egui::Color32::from_gray(200),
-            });
-        }
+/// A source serving the same text for every file.
+#[cfg(test)]
+struct FixedSource(&'static str);
 
-        for &(idx1, idx2) in &self.collisions {
-            let (point, other) = Background::get_two_mut(&mut self.points, idx1, idx2);
-            let dist = Point::distance(point, other);
-
-            if dist < PT_LINE_DISTANCE {
-                let opacity = (PT_LINE_DISTANCE - dist) / PT_LINE_DISTANCE;
-                let color =
-                    egui::Color32::from_rgba_unmultiplied(164, 171, 176, (opacity * 127.5) as u8);
-                commands.push(DrawCommand::Line {
-                    points: [
-                        egui::Pos2::new(point.x, point.y),
-                        egui::Pos2::new(other.x, other.y),
-                    ],
-                    width: 0.5,
-                    color,
-                });
-            }
-            if dist < 5.0 {
-                Point::elastic_collision(point, other);
-            }
-        }
+#[cfg(test)]
+impl ContentSource for FixedSource {
+    fn read(&mut self, _file: &str) -> Option<String> {
+        Some(self.0.to_owned())
+    }
 
-        commands
+    fn changed(&mut self) -> bool {
+        false
     }
 
-    fn render_draw_data(&mut self, painter: egui::Painter) {
-        for command in self.prepare_draw_data() {
-            match command {
-                DrawCommand::Circle {
-                    center,
-                    radius,
-                    color,
-                } => {
-                    painter.circle_filled(center, radius, color);
-                }
-                DrawCommand::Line {
-                    points,
-                    width,
-                    color,
-                } => {
-                    painter.line_segment(points, (width, color));
-                }
-            }
-        }
+    fn save(&mut self, _file: &str, _text: &str) -> Result<(), String> {
+        Ok(())
     }
 }
 
-enum DrawCommand {
-    Circle {
-        center: egui::Pos2,
-        radius: f32,
-        color: egui::Color32,
-    },
-    Line {
-        points: [egui::Pos2; 2],
-        width: f32,
-        color: egui::Color32,
-    },
+#[test]
+fn test_broken_contents() {
+    let embedded = TextContents::load(&mut content_source::EmbeddedSource, content::DEFAULT_LOCALE)
+        .unwrap()
+        .about_page
+        .text;
+    assert!(!embedded.is_empty());
+
+    // Broken from the start: the embedded contents are shown, along with the error.
+    let mut app = PortfolioApp {
+        content_source: Box::new(FixedSource("schema_version = [")),
+        parsed_text: TextContents::default(),
+        contents_loaded: false,
+        ..Default::default()
+    };
+    app.reload_contents();
+    assert!(app.content_error.is_some());
+    assert_eq!(app.parsed_text.about_page.text, embedded);
+
+    // Broken after a good version: that version stays.
+    app.content_source = Box::new(FixedSource(
+        "schema_version = 1\n[about_page]\ntext = 'edited'\n[biography]\ntext = 'bio'\n\
+         [work_experience.lucid_software]\ndescription = 'lucid'\n\
+         [work_experience.freelance_projects]\ndescription = 'freelance'\n",
+    ));
+    app.reload_contents();
+    assert_eq!(app.content_error, None);
+    assert_eq!(app.parsed_text.about_page.text, "edited");
+    app.content_source = Box::new(FixedSource("schema_version = ["));
+    app.reload_contents();
+    assert!(app.content_error.is_some());
+    assert_eq!(app.parsed_text.about_page.text, "edited");
 }
@@ -0,0 +1,535 @@
+//! Strange attractors: the orbits of a few classic systems, drawn by how often they visit every
+//! pixel.
+//!
+//! The Lorenz system flows continuously and is integrated with RK4, the Clifford and de Jong
+//! attractors are maps iterated one point at a time. Millions of points are added over a few
+//! frames and the counts are tone mapped into a texture.
+
+use std::ops::RangeInclusive;
+
+use eframe::egui;
+
+/// Width and height of the rendered image in pixels.
+const RESOLUTION: usize = 600;
+
+/// Points added to the image every frame, and in total before it is done.
+const POINTS_PER_FRAME: usize = 200_000;
+const MAX_POINTS: usize = 10_000_000;
+
+/// Size and points of the images in the gallery.
+const THUMBNAIL_RESOLUTION: usize = 96;
+const THUMBNAIL_POINTS: usize = 200_000;
+
+/// Iterations thrown away before drawing, while the orbit falls onto the attractor.
+const WARMUP: usize = 1000;
+
+/// Iterations sampled for the bounds of the image.
+const BOUNDS_SAMPLES: usize = 20_000;
+
+/// Time step of the continuous systems.
+const DT: f32 = 0.002;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum System {
+    Lorenz,
+    Clifford,
+    DeJong,
+}
+
+impl System {
+    pub const ALL: [Self; 3] = [Self::Lorenz, Self::Clifford, Self::DeJong];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lorenz => "Lorenz",
+            Self::Clifford => "Clifford",
+            Self::DeJong => "de Jong",
+        }
+    }
+
+    /// Names and slider ranges of the parameters the system uses, the first of [`Params`].
+    pub fn params(self) -> &'static [(&'static str, RangeInclusive<f32>)] {
+        match self {
+            Self::Lorenz => &[("σ", 0.0..=30.0), ("ρ", 0.0..=120.0), ("β", 0.0..=10.0)],
+            Self::Clifford | Self::DeJong => &[
+                ("a", -3.0..=3.0),
+                ("b", -3.0..=3.0),
+                ("c", -3.0..=3.0),
+                ("d", -3.0..=3.0),
+            ],
+        }
+    }
+
+    fn start(self) -> [f32; 3] {
+        match self {
+            Self::Lorenz => [1.0, 1.0, 1.0],
+            Self::Clifford | Self::DeJong => [0.1, 0.1, 0.0],
+        }
+    }
+
+    /// The next point of the orbit from `p`.
+    fn step(self, [a, b, c, d]: Params, p: [f32; 3]) -> [f32; 3] {
+        let [x, y, z] = p;
+        match self {
+            Self::Lorenz => rk4(p, DT, |[x, y, z]| {
+                [a * (y - x), x * (b - z) - y, x * y - c * z]
+            }),
+            Self::Clifford => [
+                (a * y).sin() + c * (a * x).cos(),
+                (b * x).sin() + d * (b * y).cos(),
+                z,
+            ],
+            Self::DeJong => [
+                (a * y).sin() - (b * x).cos(),
+                (c * x).sin() - (d * y).cos(),
+                z,
+            ],
+        }
+    }
+
+    /// Where `p` is drawn, with y up. The Lorenz butterfly is seen from the side.
+    fn project(self, [x, y, z]: [f32; 3]) -> egui::Pos2 {
+        match self {
+            Self::Lorenz => egui::pos2(x, z),
+            Self::Clifford | Self::DeJong => egui::pos2(x, y),
+        }
+    }
+}
+
+/// The parameters of a [`System`], those it doesn't use are ignored.
+pub type Params = [f32; 4];
+
+/// Named parameters worth looking at.
+pub const PRESETS: [(&str, System, Params); 9] = [
+    ("Butterfly", System::Lorenz, [10.0, 28.0, 8.0 / 3.0, 0.0]),
+    ("Knot", System::Lorenz, [10.0, 99.96, 8.0 / 3.0, 0.0]),
+    ("Wide wings", System::Lorenz, [16.0, 45.92, 4.0, 0.0]),
+    ("Shell", System::Clifford, [-1.4, 1.6, 1.0, 0.7]),
+    ("Lace", System::Clifford, [1.7, 1.7, 0.6, 1.2]),
+    ("Veil", System::Clifford, [-1.7, 1.3, -0.1, -1.2]),
+    ("Nebula", System::DeJong, [1.4, -2.3, 2.4, -2.1]),
+    ("Moth", System::DeJong, [-2.0, -2.0, -1.2, 2.0]),
+    ("Swirl", System::DeJong, [2.01, -2.53, 1.61, -0.33]),
+];
+
+/// One classic Runge-Kutta step of `dt` along `f` from `p`.
+pub fn rk4(p: [f32; 3], dt: f32, f: impl Fn([f32; 3]) -> [f32; 3]) -> [f32; 3] {
+    let along = |p: [f32; 3], k: [f32; 3], h: f32| [0, 1, 2].map(|i| p[i] + k[i] * h);
+    let k1 = f(p);
+    let k2 = f(along(p, k1, dt / 2.0));
+    let k3 = f(along(p, k2, dt / 2.0));
+    let k4 = f(along(p, k3, dt));
+    [0, 1, 2].map(|i| p[i] + dt / 6.0 * (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i]))
+}
+
+/// The points of an attractor, one per iteration.
+#[derive(Clone)]
+struct Orbit {
+    system: System,
+    params: Params,
+    point: [f32; 3],
+}
+
+impl Orbit {
+    /// Starts on the attractor, past the [`WARMUP`].
+    fn new(system: System, params: Params) -> Self {
+        let mut orbit = Self {
+            system,
+            params,
+            point: system.start(),
+        };
+        orbit.by_ref().take(WARMUP).for_each(drop);
+        orbit
+    }
+}
+
+impl Iterator for Orbit {
+    type Item = egui::Pos2;
+
+    fn next(&mut self) -> Option<egui::Pos2> {
+        self.point = self.system.step(self.params, self.point);
+        if !self.point.iter().all(|x| x.is_finite()) {
+            // Escaped to infinity, which would stay there.
+            self.point = self.system.start();
+        }
+        Some(self.system.project(self.point))
+    }
+}
+
+/// How often an [`Orbit`] visited every pixel of a square image.
+struct Density {
+    resolution: usize,
+    /// The part of the plane shown, a square.
+    bounds: egui::Rect,
+    counts: Vec<u32>,
+    max: u32,
+    points: usize,
+}
+
+impl Density {
+    /// An empty image fitting the attractor `orbit` will draw.
+    fn new(resolution: usize, orbit: &Orbit) -> Self {
+        let bounds = egui::Rect::from_points(
+            &orbit
+                .clone()
+                .take(BOUNDS_SAMPLES)
+                .filter(|p| p.x.is_finite() && p.y.is_finite())
+                .collect::<Vec<_>>(),
+        );
+        let size = bounds.size().max_elem().max(1e-3) * 1.1;
+        Self {
+            resolution,
+            bounds: egui::Rect::from_center_size(bounds.center(), egui::Vec2::splat(size)),
+            counts: vec![0; resolution * resolution],
+            max: 0,
+            points: 0,
+        }
+    }
+
+    fn add(&mut self, orbit: &mut Orbit, points: usize) {
+        let scale = self.resolution as f32 / self.bounds.width();
+        for p in orbit.take(points) {
+            let column = (p.x - self.bounds.left()) * scale;
+            let row = (self.bounds.bottom() - p.y) * scale;
+            // Also false for NaN.
+            if (0.0..self.resolution as f32).contains(&column)
+                && (0.0..self.resolution as f32).contains(&row)
+            {
+                let count = &mut self.counts[row as usize * self.resolution + column as usize];
+                *count += 1;
+                self.max = self.max.max(*count);
+            }
+        }
+        self.points += points;
+    }
+
+    /// The counts tone mapped into `color`, logarithmically since they span many orders of
+    /// magnitude, then with `gamma` to bring out the faint parts.
+    fn image(&self, color: egui::Color32, gamma: f32) -> egui::ColorImage {
+        let log_max = (1.0 + self.max as f32).ln().max(f32::EPSILON);
+        let pixels = self
+            .counts
+            .iter()
+            .map(|&count| {
+                let level = ((1.0 + count as f32).ln() / log_max).powf(1.0 / gamma);
+                tone(color, level)
+            })
+            .collect();
+        egui::ColorImage {
+            size: [self.resolution; 2],
+            pixels,
+        }
+    }
+}
+
+/// Black at 0.0, `color` at 0.75 and white at 1.0.
+fn tone(color: egui::Color32, level: f32) -> egui::Color32 {
+    const KNEE: f32 = 0.75;
+    let channel = |c: u8| {
+        let c = c as f32;
+        let value = if level < KNEE {
+            c * level / KNEE
+        } else {
+            c + (255.0 - c) * (level - KNEE) / (1.0 - KNEE)
+        };
+        value.round().clamp(0.0, 255.0) as u8
+    };
+    egui::Color32::from_rgb(channel(color.r()), channel(color.g()), channel(color.b()))
+}
+
+/// Encode `image` as a PNG file.
+#[cfg(not(target_arch = "wasm32"))]
+fn encode_png(image: &egui::ColorImage) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, image.width() as u32, image.height() as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    writer
+        .write_image_data(image.as_raw())
+        .map_err(|err| err.to_string())?;
+    writer.finish().map_err(|err| err.to_string())?;
+    Ok(bytes)
+}
+
+/// The window drawing an attractor, with sliders for its parameters, a gallery of
+/// [`PRESETS`] and, natively, saving the image.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct AttractorWindow {
+    pub open: bool,
+    system: System,
+    params: Params,
+    color: egui::Color32,
+    gamma: f32,
+
+    #[serde(skip)]
+    orbit: Option<Orbit>,
+    #[serde(skip)]
+    density: Option<Density>,
+    #[serde(skip)]
+    texture: Option<egui::TextureHandle>,
+    #[serde(skip)]
+    thumbnails: Vec<egui::TextureHandle>,
+
+    /// Whether the texture has to be tone mapped again.
+    #[serde(skip)]
+    dirty: bool,
+
+    /// Where the image was saved, or why it couldn't be.
+    #[serde(skip)]
+    saved: Option<Result<String, String>>,
+}
+
+impl Default for AttractorWindow {
+    fn default() -> Self {
+        let (_, system, params) = PRESETS[0];
+        Self {
+            open: false,
+            system,
+            params,
+            color: egui::Color32::from_rgb(230, 120, 40),
+            gamma: 2.0,
+            orbit: None,
+            density: None,
+            texture: None,
+            thumbnails: Vec::new(),
+            dirty: false,
+            saved: None,
+        }
+    }
+}
+
+impl AttractorWindow {
+    /// Start the image over, for new parameters.
+    fn restart(&mut self) {
+        self.orbit = None;
+        self.density = None;
+        self.saved = None;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.render(ctx);
+
+        let mut open = self.open;
+        egui::Window::new("Strange attractors")
+            .open(&mut open)
+            .auto_sized()
+            .show(ctx, |ui| self.ui(ui));
+        self.open = open;
+        if !self.open {
+            // Free the memory of a window nobody looks at.
+            self.restart();
+            self.texture = None;
+            self.thumbnails.clear();
+        }
+    }
+
+    /// Add the points of this frame and update the texture.
+    fn render(&mut self, ctx: &egui::Context) {
+        let orbit = self
+            .orbit
+            .get_or_insert_with(|| Orbit::new(self.system, self.params));
+        let density = self
+            .density
+            .get_or_insert_with(|| Density::new(RESOLUTION, orbit));
+        if density.points < MAX_POINTS {
+            density.add(orbit, POINTS_PER_FRAME);
+            self.dirty = true;
+            ctx.request_repaint();
+        }
+        if self.dirty {
+            let image = density.image(self.color, self.gamma);
+            match &mut self.texture {
+                Some(texture) => texture.set(image, egui::TextureOptions::LINEAR),
+                None => {
+                    self.texture =
+                        Some(ctx.load_texture("attractor", image, egui::TextureOptions::LINEAR));
+                }
+            }
+            self.dirty = false;
+        }
+
+        if self.thumbnails.is_empty() {
+            self.thumbnails = PRESETS
+                .iter()
+                .map(|&(name, system, params)| {
+                    let mut orbit = Orbit::new(system, params);
+                    let mut density = Density::new(THUMBNAIL_RESOLUTION, &orbit);
+                    density.add(&mut orbit, THUMBNAIL_POINTS);
+                    let image = density.image(self.color, self.gamma);
+                    ctx.load_texture(name, image, egui::TextureOptions::LINEAR)
+                })
+                .collect();
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        let before = (self.system, self.params);
+        let look = (self.color, self.gamma);
+
+        egui::Grid::new("attractor_settings")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("System");
+                ui.horizontal(|ui| {
+                    for system in System::ALL {
+                        if ui
+                            .selectable_label(self.system == system, system.name())
+                            .clicked()
+                            && self.system != system
+                        {
+                            let (_, _, params) =
+                                PRESETS.iter().find(|(_, s, _)| *s == system).unwrap();
+                            self.system = system;
+                            self.params = *params;
+                        }
+                    }
+                });
+                ui.end_row();
+
+                for ((name, range), param) in self.system.params().iter().zip(&mut self.params) {
+                    ui.label(*name);
+                    ui.add(egui::Slider::new(param, range.clone()).max_decimals(3));
+                    ui.end_row();
+                }
+
+                ui.label("Color");
+                egui::color_picker::color_edit_button_srgba(
+                    ui,
+                    &mut self.color,
+                    egui::color_picker::Alpha::Opaque,
+                );
+                ui.end_row();
+
+                ui.label("Gamma")
+                    .on_hover_text("Higher shows more of the rarely visited parts");
+                ui.add(egui::Slider::new(&mut self.gamma, 0.5..=5.0));
+                ui.end_row();
+            });
+
+        if let Some(texture) = &self.texture {
+            ui.add(egui::Image::new((texture.id(), egui::Vec2::splat(400.0))));
+        }
+        ui.horizontal(|ui| {
+            if let Some(density) = &self.density {
+                let millions = density.points as f32 / 1e6;
+                ui.label(format!("{millions:.1} million points"));
+                if density.points < MAX_POINTS {
+                    ui.spinner();
+                }
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.button("💾 Save PNG").clicked() {
+                self.saved = Some(self.save_png());
+            }
+        });
+        match &self.saved {
+            Some(Ok(path)) => {
+                ui.label(format!("Saved to {path}"));
+            }
+            Some(Err(err)) => {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+            None => {}
+        }
+
+        ui.collapsing("Gallery", |ui| {
+            egui::Grid::new("attractor_gallery").show(ui, |ui| {
+                for (i, (&(name, system, params), thumbnail)) in
+                    PRESETS.iter().zip(&self.thumbnails).enumerate()
+                {
+                    let size = egui::Vec2::splat(THUMBNAIL_RESOLUTION as f32);
+                    let button = egui::ImageButton::new((thumbnail.id(), size))
+                        .selected(self.system == system && self.params == params);
+                    if ui
+                        .add(button)
+                        .on_hover_text(format!("{name} ({})", system.name()))
+                        .clicked()
+                    {
+                        self.system = system;
+                        self.params = params;
+                    }
+                    if i % 3 == 2 {
+                        ui.end_row();
+                    }
+                }
+            });
+        });
+
+        if (self.system, self.params) != before {
+            self.restart();
+        } else if (self.color, self.gamma) != look {
+            self.dirty = true;
+            self.saved = None;
+        }
+    }
+
+    /// Write the image into the working directory, returning its path.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_png(&self) -> Result<String, String> {
+        let density = self.density.as_ref().ok_or("Nothing drawn yet.")?;
+        let bytes = encode_png(&density.image(self.color, self.gamma))?;
+        let file = format!(
+            "{}.png",
+            self.system.name().to_lowercase().replace(' ', "_")
+        );
+        let path = std::env::current_dir()
+            .map_err(|err| err.to_string())?
+            .join(file);
+        std::fs::write(&path, bytes).map_err(|err| format!("{}: {err}", path.display()))?;
+        Ok(path.display().to_string())
+    }
+}
+
+#[test]
+fn test_rk4() {
+    // x' = -x is x = e^-t, which RK4 follows to a tiny error per step.
+    let mut p = [1.0, 2.0, 0.0];
+    for _ in 0..100 {
+        p = rk4(p, 0.01, |[x, y, z]| [-x, -y, z]);
+    }
+    assert!((p[0] - (-1.0f32).exp()).abs() < 1e-6, "{p:?}");
+    assert!((p[1] - 2.0 * (-1.0f32).exp()).abs() < 1e-6, "{p:?}");
+
+    // The butterfly stays on its wings, around the two fixed points at z = ρ - 1.
+    let mut orbit = Orbit::new(System::Lorenz, PRESETS[0].2);
+    assert!(orbit
+        .by_ref()
+        .take(10_000)
+        .all(|p| p.x.abs() < 25.0 && (0.0..55.0).contains(&p.y)));
+}
+
+#[test]
+fn test_density() {
+    for (name, system, params) in PRESETS {
+        let mut orbit = Orbit::new(system, params);
+        let mut density = Density::new(64, &orbit);
+        density.add(&mut orbit, 50_000);
+        // Most points land inside the bounds, and not all on one pixel.
+        let inside: u32 = density.counts.iter().sum();
+        let visited = density.counts.iter().filter(|&&c| c > 0).count();
+        assert!(inside > 45_000, "{name}: {inside}");
+        assert!(visited > 200, "{name}: {visited}");
+
+        let image = density.image(egui::Color32::RED, 2.0);
+        assert_eq!(image.size, [64, 64]);
+        assert!(image.pixels.contains(&egui::Color32::WHITE), "{name}");
+        assert!(image.pixels.contains(&egui::Color32::BLACK), "{name}");
+    }
+
+    assert_eq!(tone(egui::Color32::RED, 0.75), egui::Color32::RED);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn test_encode_png() {
+    let image = egui::ColorImage::new([3, 2], egui::Color32::from_rgb(1, 2, 3));
+    let bytes = encode_png(&image).unwrap();
+    let decoder = png::Decoder::new(bytes.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((info.width, info.height), (3, 2));
+    assert_eq!(&pixels[..8], [1, 2, 3, 255, 1, 2, 3, 255]);
+}
@@ -0,0 +1,163 @@
+//! Playing generated sound: through the default output device natively, with the
+//! `native-audio` feature, and through Web Audio on the web.
+
+use std::sync::{Arc, Mutex};
+
+/// Something producing mono samples on the audio thread.
+pub trait Synth: Send + 'static {
+    /// Called before the first [`Self::fill`], with the rate of the output in Hz.
+    fn set_sample_rate(&mut self, sample_rate: f32);
+
+    /// Write the next samples, between -1.0 and 1.0, into `out`.
+    fn fill(&mut self, out: &mut [f32]);
+}
+
+/// Sound playing until dropped.
+pub struct AudioOutput {
+    #[cfg(all(not(target_arch = "wasm32"), feature = "native-audio"))]
+    _stream: cpal::Stream,
+
+    #[cfg(target_arch = "wasm32")]
+    context: web_sys::AudioContext,
+
+    /// Some browsers stop calling nodes nothing refers to.
+    #[cfg(target_arch = "wasm32")]
+    _processor: web_sys::ScriptProcessorNode,
+
+    /// Keeps the callback alive as long as the node calls it.
+    #[cfg(target_arch = "wasm32")]
+    _on_audio_process: wasm_bindgen::closure::Closure<dyn FnMut(web_sys::AudioProcessingEvent)>,
+}
+
+/// Run `synth` on the audio thread, with silence whenever its lock is poisoned.
+#[cfg(any(target_arch = "wasm32", feature = "native-audio"))]
+fn fill(synth: &Mutex<dyn Synth>, out: &mut [f32]) {
+    match synth.lock() {
+        Ok(mut synth) => synth.fill(out),
+        Err(_) => out.fill(0.0),
+    }
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "native-audio")))]
+impl AudioOutput {
+    pub fn start(_synth: Arc<Mutex<dyn Synth>>) -> Result<Self, String> {
+        Err("This build has no sound, build it with the `native-audio` feature.".to_owned())
+    }
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "native-audio"))]
+impl AudioOutput {
+    /// Play `synth` on the default output device, the same samples on every channel.
+    pub fn start(synth: Arc<Mutex<dyn Synth>>) -> Result<Self, String> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("There is no audio output device.")?;
+        let supported = device
+            .default_output_config()
+            .map_err(|err| err.to_string())?;
+        let config = supported.config();
+        if let Ok(mut synth) = synth.lock() {
+            synth.set_sample_rate(config.sample_rate.0 as f32);
+        }
+
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config, synth),
+            cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config, synth),
+            cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config, synth),
+            format => Err(format!("Unsupported sample format {format}.")),
+        }?;
+        stream.play().map_err(|err| err.to_string())?;
+        Ok(Self { _stream: stream })
+    }
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "native-audio"))]
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    synth: Arc<Mutex<dyn Synth>>,
+) -> Result<cpal::Stream, String>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    use cpal::traits::DeviceTrait;
+    use cpal::Sample;
+
+    let channels = config.channels as usize;
+    let mut mono = Vec::new();
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _| {
+                mono.resize(data.len() / channels, 0.0);
+                fill(&synth, &mut mono);
+                for (frame, &sample) in data.chunks_mut(channels).zip(&mono) {
+                    frame.fill(T::from_sample(sample));
+                }
+            },
+            |err| log::error!("Audio output failed: {err}"),
+            None,
+        )
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+impl AudioOutput {
+    /// Frames asked for per callback, large enough not to stutter while a frame is drawn.
+    const BUFFER_SIZE: u32 = 4096;
+
+    /// Play `synth` through a Web Audio script processor.
+    ///
+    /// Browsers only allow this in response to a click or key press.
+    pub fn start(synth: Arc<Mutex<dyn Synth>>) -> Result<Self, String> {
+        use wasm_bindgen::JsCast as _;
+
+        let js_error = |err: wasm_bindgen::JsValue| format!("{err:?}");
+        let context = web_sys::AudioContext::new().map_err(js_error)?;
+        if let Ok(mut synth) = synth.lock() {
+            synth.set_sample_rate(context.sample_rate());
+        }
+        let processor = context
+            .create_script_processor_with_buffer_size_and_number_of_input_channels_and_number_of_output_channels(
+                Self::BUFFER_SIZE,
+                0,
+                1,
+            )
+            .map_err(js_error)?;
+
+        let mut samples = vec![0.0; Self::BUFFER_SIZE as usize];
+        let on_audio_process = wasm_bindgen::closure::Closure::<
+            dyn FnMut(web_sys::AudioProcessingEvent),
+        >::new(move |event: web_sys::AudioProcessingEvent| {
+            let Ok(buffer) = event.output_buffer() else {
+                return;
+            };
+            samples.resize(buffer.length() as usize, 0.0);
+            fill(&synth, &mut samples);
+            if let Err(err) = buffer.copy_to_channel(&samples, 0) {
+                log::error!("Audio output failed: {err:?}");
+            }
+        });
+        processor.set_onaudioprocess(Some(on_audio_process.as_ref().unchecked_ref()));
+        processor
+            .connect_with_audio_node(&context.destination())
+            .map_err(js_error)?;
+        // A context created outside of a click starts suspended.
+        _ = context.resume();
+
+        Ok(Self {
+            context,
+            _processor: processor,
+            _on_audio_process: on_audio_process,
+        })
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Drop for AudioOutput {
+    fn drop(&mut self) {
+        _ = self.context.close();
+    }
+}
@@ -0,0 +1,347 @@
+//! Makes the [`crate::Background`] move to an [`AudioSource`]: the loudness of its bass, mids
+//! and highs, measured with an FFT every frame.
+
+use eframe::egui;
+
+use crate::audio_source::{AudioSource, Noise, Tone, Wav, WavLoop};
+use crate::dsp::{self, Complex, Window};
+
+/// Samples analyzed every frame, the most recent ones.
+const FFT_SIZE: usize = 2048;
+
+/// Seconds for a band to fall to a third of its level once the sound stops, it rises at once.
+const RELEASE: f32 = 0.15;
+
+/// Frequency ranges of the bands in Hz.
+const BASS: (f32, f32) = (20.0, 250.0);
+const MIDS: (f32, f32) = (250.0, 4000.0);
+const HIGHS: (f32, f32) = (4000.0, 16_000.0);
+
+/// How loud each part of the spectrum is.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bands {
+    pub bass: f32,
+    pub mids: f32,
+    pub highs: f32,
+}
+
+impl Bands {
+    fn map(self, f: impl Fn(f32) -> f32) -> Self {
+        Self {
+            bass: f(self.bass),
+            mids: f(self.mids),
+            highs: f(self.highs),
+        }
+    }
+
+    /// Levels from [`BandAnalyzer`] as loudness between 0.0 and 1.0, reaching 1.0 sooner at
+    /// a higher `sensitivity`.
+    pub fn loudness(self, sensitivity: f32) -> Self {
+        self.map(|rms| 1.0 - (-rms * sensitivity).exp())
+    }
+}
+
+/// Measures the [`Bands`] of a signal as it comes in.
+pub struct BandAnalyzer {
+    /// The last [`FFT_SIZE`] samples.
+    history: Vec<f32>,
+    window: Vec<f32>,
+    spectrum: Vec<Complex>,
+
+    /// RMS of every band, falling slowly.
+    levels: Bands,
+}
+
+impl Default for BandAnalyzer {
+    fn default() -> Self {
+        Self {
+            history: vec![0.0; FFT_SIZE],
+            window: Window::Hann.coefficients(FFT_SIZE),
+            spectrum: vec![Complex::ZERO; FFT_SIZE],
+            levels: Bands::default(),
+        }
+    }
+}
+
+impl BandAnalyzer {
+    /// Add the `samples` of the last `dt` seconds and measure the RMS of every band.
+    pub fn analyze(&mut self, samples: &[f32], sample_rate: f32, dt: f32) -> Bands {
+        self.history.extend_from_slice(samples);
+        let excess = self.history.len() - FFT_SIZE;
+        self.history.drain(..excess);
+
+        for ((bin, &x), &w) in self
+            .spectrum
+            .iter_mut()
+            .zip(&self.history)
+            .zip(&self.window)
+        {
+            *bin = Complex::new(x * w, 0.0);
+        }
+        dsp::fft(&mut self.spectrum);
+
+        // By Parseval the bins of the positive frequencies hold half the energy of the
+        // windowed samples, times the length.
+        let window_energy: f32 = self.window.iter().map(|w| w * w).sum();
+        let hz = sample_rate / FFT_SIZE as f32;
+        let rms = |(low, high): (f32, f32)| {
+            let start = ((low / hz).ceil() as usize).max(1);
+            let end = ((high / hz) as usize).min(FFT_SIZE / 2);
+            let energy: f32 = self.spectrum[start.min(end)..end]
+                .iter()
+                .map(|bin| bin.norm_sqr())
+                .sum();
+            (2.0 * energy / (FFT_SIZE as f32 * window_energy)).sqrt()
+        };
+        let measured = Bands {
+            bass: rms(BASS),
+            mids: rms(MIDS),
+            highs: rms(HIGHS),
+        };
+
+        let fall = (-dt / RELEASE).exp();
+        let old = self.levels;
+        self.levels = Bands {
+            bass: measured.bass.max(old.bass * fall),
+            mids: measured.mids.max(old.mids * fall),
+            highs: measured.highs.max(old.highs * fall),
+        };
+        self.levels
+    }
+}
+
+/// Where the sound the background reacts to comes from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SourceKind {
+    #[default]
+    Off,
+    Tone,
+    Noise,
+    Beat,
+    Microphone,
+}
+
+impl SourceKind {
+    pub const ALL: [Self; 5] = [
+        Self::Off,
+        Self::Tone,
+        Self::Noise,
+        Self::Beat,
+        Self::Microphone,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Tone => "Bass tone",
+            Self::Noise => "Noise",
+            Self::Beat => "Drum loop",
+            Self::Microphone => "Microphone",
+        }
+    }
+
+    /// The microphone is only there on the web.
+    pub fn available(self) -> bool {
+        self != Self::Microphone || cfg!(target_arch = "wasm32")
+    }
+
+    fn start(self) -> Result<Option<Box<dyn AudioSource>>, String> {
+        const SAMPLE_RATE: f32 = 48_000.0;
+        Ok(Some(match self {
+            Self::Off => return Ok(None),
+            Self::Tone => Box::new(Tone::new(60.0, 0.5, SAMPLE_RATE)),
+            Self::Noise => Box::new(Noise::new(0.3, SAMPLE_RATE, rand::random())),
+            Self::Beat => Box::new(WavLoop::new(Wav::beat())),
+            #[cfg(target_arch = "wasm32")]
+            Self::Microphone => Box::new(crate::audio_source::Microphone::start()?),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Microphone => {
+                return Err("There is no microphone outside the browser.".to_owned())
+            }
+        }))
+    }
+}
+
+/// The audio the background reacts to, and the settings for it.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct AudioReactive {
+    /// How loud a sound has to be to move the background fully.
+    pub sensitivity: f32,
+
+    /// Starts off every time, rather than asking for the microphone on every visit.
+    #[serde(skip)]
+    kind: SourceKind,
+    #[serde(skip)]
+    source: Option<Box<dyn AudioSource>>,
+    #[serde(skip)]
+    analyzer: BandAnalyzer,
+    #[serde(skip)]
+    samples: Vec<f32>,
+
+    /// Measured last frame, for the meters.
+    #[serde(skip)]
+    bands: Option<Bands>,
+
+    /// Why the source couldn't start or stopped.
+    #[serde(skip)]
+    error: Option<String>,
+}
+
+impl Default for AudioReactive {
+    fn default() -> Self {
+        Self {
+            sensitivity: 10.0,
+            kind: SourceKind::Off,
+            source: None,
+            analyzer: BandAnalyzer::default(),
+            samples: Vec::new(),
+            bands: None,
+            error: None,
+        }
+    }
+}
+
+impl AudioReactive {
+    pub fn set_source(&mut self, kind: SourceKind) {
+        self.kind = kind;
+        self.analyzer = BandAnalyzer::default();
+        self.bands = None;
+        match kind.start() {
+            Ok(source) => {
+                self.source = source;
+                self.error = None;
+            }
+            Err(err) => {
+                self.source = None;
+                self.kind = SourceKind::Off;
+                self.error = Some(err);
+            }
+        }
+    }
+
+    /// Read the `dt` seconds of audio since the last frame and measure how loud its bands
+    /// are, `None` without a source.
+    pub fn update(&mut self, dt: f32) -> Option<Bands> {
+        let source = self.source.as_mut()?;
+        if let Some(err) = source.error() {
+            self.set_source(SourceKind::Off);
+            self.error = Some(err);
+            return None;
+        }
+        self.samples.clear();
+        source.read(dt, &mut self.samples);
+        let levels = self
+            .analyzer
+            .analyze(&self.samples, source.sample_rate(), dt);
+        self.bands = Some(levels.loudness(self.sensitivity));
+        self.bands
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("audio_reactive")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("React to").on_hover_text(
+                    "Bass pulls the points down, mids brighten the links, highs spawn bursts",
+                );
+                let mut kind = self.kind;
+                egui::ComboBox::from_id_source("audio_source")
+                    .selected_text(kind.name())
+                    .show_ui(ui, |ui| {
+                        for option in SourceKind::ALL.into_iter().filter(|k| k.available()) {
+                            ui.selectable_value(&mut kind, option, option.name());
+                        }
+                    });
+                if kind != self.kind {
+                    self.set_source(kind);
+                }
+                ui.end_row();
+
+                ui.label("Sensitivity");
+                ui.add(egui::Slider::new(&mut self.sensitivity, 1.0..=100.0).logarithmic(true));
+                ui.end_row();
+
+                if let Some(bands) = self.bands {
+                    for (name, level) in [
+                        ("Bass", bands.bass),
+                        ("Mids", bands.mids),
+                        ("Highs", bands.highs),
+                    ] {
+                        ui.label(name);
+                        ui.add(egui::ProgressBar::new(level).desired_width(150.0));
+                        ui.end_row();
+                    }
+                }
+            });
+        if let Some(err) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+    }
+}
+
+#[test]
+fn test_band_analyzer() {
+    const DT: f32 = 1.0 / 60.0;
+    // Levels after a second of `source`.
+    let levels = |mut source: Box<dyn AudioSource>| {
+        let mut analyzer = BandAnalyzer::default();
+        let mut samples = Vec::new();
+        let mut levels = Bands::default();
+        for _ in 0..60 {
+            samples.clear();
+            source.read(DT, &mut samples);
+            levels = analyzer.analyze(&samples, source.sample_rate(), DT);
+        }
+        levels
+    };
+    let near = |level: f32, expected: f32| (level - expected).abs() < 0.03;
+    let sine_rms = 0.5 * std::f32::consts::FRAC_1_SQRT_2;
+
+    let bass = levels(Box::new(Tone::new(60.0, 0.5, 48_000.0)));
+    assert!(
+        near(bass.bass, sine_rms) && bass.mids < 0.01 && bass.highs < 0.01,
+        "{bass:?}"
+    );
+    let mids = levels(Box::new(Tone::new(1000.0, 0.5, 48_000.0)));
+    assert!(
+        mids.bass < 0.01 && near(mids.mids, sine_rms) && mids.highs < 0.01,
+        "{mids:?}"
+    );
+    let highs = levels(Box::new(Tone::new(8000.0, 0.5, 44_100.0)));
+    assert!(
+        highs.bass < 0.01 && highs.mids < 0.01 && near(highs.highs, sine_rms),
+        "{highs:?}"
+    );
+
+    // White noise is as loud at every frequency, so the widest band gets the most of it.
+    let noise = levels(Box::new(Noise::new(0.3, 48_000.0, 3)));
+    assert!(
+        noise.highs > noise.mids && noise.mids > noise.bass,
+        "{noise:?}"
+    );
+
+    assert_eq!(Bands::default().loudness(10.0), Bands::default());
+    assert!(bass.loudness(10.0).bass > 0.9);
+}
+
+#[test]
+fn test_drum_loop_hits() {
+    // The loop has four kicks on the beats and four hi-hats between them, every two seconds.
+    let mut source = WavLoop::new(Wav::beat());
+    let mut analyzer = BandAnalyzer::default();
+    let mut samples = Vec::new();
+    let (mut kicks, mut hats) = (0, 0);
+    let mut last = Bands::default();
+    for _ in 0..120 {
+        samples.clear();
+        source.read(1.0 / 60.0, &mut samples);
+        let bands = analyzer
+            .analyze(&samples, source.sample_rate(), 1.0 / 60.0)
+            .loudness(10.0);
+        kicks += (bands.bass >= 0.5 && last.bass < 0.5) as u32;
+        hats += (bands.highs >= 0.3 && last.highs < 0.3) as u32;
+        last = bands;
+    }
+    assert_eq!((kicks, hats), (4, 4));
+}
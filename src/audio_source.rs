@@ -0,0 +1,395 @@
+//! Signals the background can react to: generated tones and noise, a WAV file, or the
+//! microphone on the web.
+
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+
+/// The drum loop bundled with the app.
+const BEAT_WAV: &[u8] = include_bytes!("../assets/beat.wav");
+
+/// A mono signal read as time goes by.
+pub trait AudioSource {
+    fn sample_rate(&self) -> f32;
+
+    /// Append the samples of the `dt` seconds since the last call to `out`.
+    fn read(&mut self, dt: f32, out: &mut Vec<f32>);
+
+    /// Why the source stopped delivering samples, for sources that start in the background.
+    fn error(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Number of whole samples in `dt` seconds, carrying the fraction left over to the next call.
+fn samples_in(dt: f32, sample_rate: f32, carry: &mut f32) -> usize {
+    let samples = dt.max(0.0) * sample_rate + *carry;
+    *carry = samples.fract();
+    samples as usize
+}
+
+/// A sine wave.
+pub struct Tone {
+    pub frequency: f32,
+    pub amplitude: f32,
+    sample_rate: f32,
+    phase: f32,
+    carry: f32,
+}
+
+impl Tone {
+    pub fn new(frequency: f32, amplitude: f32, sample_rate: f32) -> Self {
+        Self {
+            frequency,
+            amplitude,
+            sample_rate,
+            phase: 0.0,
+            carry: 0.0,
+        }
+    }
+}
+
+impl AudioSource for Tone {
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    fn read(&mut self, dt: f32, out: &mut Vec<f32>) {
+        let step = std::f32::consts::TAU * self.frequency / self.sample_rate;
+        for _ in 0..samples_in(dt, self.sample_rate, &mut self.carry) {
+            out.push(self.amplitude * self.phase.sin());
+            self.phase = (self.phase + step) % std::f32::consts::TAU;
+        }
+    }
+}
+
+/// White noise, equally loud at every frequency.
+pub struct Noise {
+    pub amplitude: f32,
+    sample_rate: f32,
+    carry: f32,
+    rng: ChaCha20Rng,
+}
+
+impl Noise {
+    pub fn new(amplitude: f32, sample_rate: f32, seed: u64) -> Self {
+        Self {
+            amplitude,
+            sample_rate,
+            carry: 0.0,
+            rng: ChaCha20Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl AudioSource for Noise {
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    fn read(&mut self, dt: f32, out: &mut Vec<f32>) {
+        for _ in 0..samples_in(dt, self.sample_rate, &mut self.carry) {
+            out.push(self.amplitude * self.rng.gen_range(-1.0..=1.0));
+        }
+    }
+}
+
+/// Decoded PCM samples of a WAV file, mixed down to mono.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wav {
+    pub sample_rate: f32,
+    pub samples: Vec<f32>,
+}
+
+impl Wav {
+    /// Decode a RIFF WAV file of 8, 16, 24 or 32 bit integer or 32 bit float samples.
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err("Not a WAV file.".to_owned());
+        }
+
+        let mut format = None;
+        let mut data = None;
+        let mut rest = &bytes[12..];
+        while rest.len() >= 8 {
+            let id = &rest[0..4];
+            let len = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+            let body = rest.get(8..8 + len).ok_or("The WAV file is cut off.")?;
+            match id {
+                b"fmt " if len >= 16 => format = Some(body),
+                b"data" => data = Some(body),
+                _ => {}
+            }
+            // Chunks are padded to an even length.
+            rest = rest.get(8 + len + len % 2..).unwrap_or_default();
+        }
+        let format = format.ok_or("The WAV file has no format.")?;
+        let data = data.ok_or("The WAV file has no samples.")?;
+
+        let u16_at = |i: usize| u16::from_le_bytes([format[i], format[i + 1]]);
+        let tag = u16_at(0);
+        let channels = u16_at(2) as usize;
+        let sample_rate = u32::from_le_bytes([format[4], format[5], format[6], format[7]]);
+        let bits = u16_at(14);
+        // The extensible format keeps the real tag at the start of its sub-format GUID.
+        let tag = if tag == 0xfffe && format.len() >= 26 {
+            u16_at(24)
+        } else {
+            tag
+        };
+
+        let decode: fn(&[u8]) -> f32 = match (tag, bits) {
+            (1, 8) => |b| (b[0] as f32 - 128.0) / 128.0,
+            (1, 16) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+            (1, 24) => |b| i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2_147_483_648.0,
+            (1, 32) => |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0,
+            (3, 32) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            _ => return Err(format!("Unsupported WAV format {tag} with {bits} bits.")),
+        };
+        if channels == 0 || sample_rate == 0 {
+            return Err("The WAV file has no channels.".to_owned());
+        }
+
+        let frame_size = channels * bits as usize / 8;
+        let samples = data
+            .chunks_exact(frame_size)
+            .map(|frame| {
+                frame
+                    .chunks_exact(bits as usize / 8)
+                    .map(decode)
+                    .sum::<f32>()
+                    / channels as f32
+            })
+            .collect();
+        Ok(Self {
+            sample_rate: sample_rate as f32,
+            samples,
+        })
+    }
+
+    /// The drum loop in `assets/beat.wav`.
+    pub fn beat() -> Self {
+        Self::decode(BEAT_WAV).expect("assets/beat.wav is a valid WAV file")
+    }
+}
+
+/// A [`Wav`] played in a loop.
+pub struct WavLoop {
+    wav: Wav,
+    position: usize,
+    carry: f32,
+}
+
+impl WavLoop {
+    pub fn new(wav: Wav) -> Self {
+        Self {
+            wav,
+            position: 0,
+            carry: 0.0,
+        }
+    }
+}
+
+impl AudioSource for WavLoop {
+    fn sample_rate(&self) -> f32 {
+        self.wav.sample_rate
+    }
+
+    fn read(&mut self, dt: f32, out: &mut Vec<f32>) {
+        if self.wav.samples.is_empty() {
+            return;
+        }
+        for _ in 0..samples_in(dt, self.wav.sample_rate, &mut self.carry) {
+            out.push(self.wav.samples[self.position]);
+            self.position = (self.position + 1) % self.wav.samples.len();
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub use microphone::Microphone;
+
+#[cfg(target_arch = "wasm32")]
+mod microphone {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::{JsCast as _, JsValue};
+
+    use super::AudioSource;
+
+    /// Frames captured per callback.
+    const BUFFER_SIZE: u32 = 2048;
+
+    /// Most samples kept between reads.
+    const MAX_CAPTURED: usize = 1 << 16;
+
+    /// What the microphone picked up, through Web Audio.
+    pub struct Microphone {
+        context: web_sys::AudioContext,
+
+        /// Samples captured since the last read.
+        captured: Rc<RefCell<Vec<f32>>>,
+
+        /// Set when the visitor or the browser refused access.
+        error: Rc<RefCell<Option<String>>>,
+
+        _processor: web_sys::ScriptProcessorNode,
+        _on_audio_process: Closure<dyn FnMut(web_sys::AudioProcessingEvent)>,
+    }
+
+    impl Microphone {
+        /// Ask for the microphone, which browsers only allow in response to a click or key
+        /// press. Samples arrive once the visitor agreed.
+        pub fn start() -> Result<Self, String> {
+            let js_error = |err: JsValue| format!("{err:?}");
+            let context = web_sys::AudioContext::new().map_err(js_error)?;
+            let processor = context
+                .create_script_processor_with_buffer_size_and_number_of_input_channels_and_number_of_output_channels(
+                    BUFFER_SIZE,
+                    1,
+                    1,
+                )
+                .map_err(js_error)?;
+
+            let captured = Rc::new(RefCell::new(Vec::new()));
+            let on_audio_process = Closure::<dyn FnMut(web_sys::AudioProcessingEvent)>::new({
+                let captured = captured.clone();
+                move |event: web_sys::AudioProcessingEvent| {
+                    if let Ok(input) = event.input_buffer() {
+                        if let Ok(samples) = input.get_channel_data(0) {
+                            let mut captured = captured.borrow_mut();
+                            captured.extend(samples);
+                            // Nobody reads while the background stands still.
+                            let excess = captured.len().saturating_sub(MAX_CAPTURED);
+                            captured.drain(..excess);
+                        }
+                    }
+                }
+            });
+            processor.set_onaudioprocess(Some(on_audio_process.as_ref().unchecked_ref()));
+            // Processors only run while connected to the output, they play nothing.
+            processor
+                .connect_with_audio_node(&context.destination())
+                .map_err(js_error)?;
+            _ = context.resume();
+
+            let media_devices = web_sys::window()
+                .ok_or("No window.")?
+                .navigator()
+                .media_devices()
+                .map_err(js_error)?;
+            let mut constraints = web_sys::MediaStreamConstraints::new();
+            constraints.audio(&JsValue::TRUE);
+            let stream = media_devices
+                .get_user_media_with_constraints(&constraints)
+                .map_err(js_error)?;
+
+            let error = Rc::new(RefCell::new(None));
+            wasm_bindgen_futures::spawn_local({
+                let (context, processor, error) =
+                    (context.clone(), processor.clone(), error.clone());
+                async move {
+                    let connected = async {
+                        let stream: web_sys::MediaStream =
+                            wasm_bindgen_futures::JsFuture::from(stream)
+                                .await?
+                                .dyn_into()?;
+                        context
+                            .create_media_stream_source(&stream)?
+                            .connect_with_audio_node(&processor)?;
+                        Ok::<_, JsValue>(())
+                    };
+                    if let Err(err) = connected.await {
+                        *error.borrow_mut() = Some(format!("No microphone: {err:?}"));
+                    }
+                }
+            });
+
+            Ok(Self {
+                context,
+                captured,
+                error,
+                _processor: processor,
+                _on_audio_process: on_audio_process,
+            })
+        }
+    }
+
+    impl AudioSource for Microphone {
+        fn sample_rate(&self) -> f32 {
+            self.context.sample_rate()
+        }
+
+        fn read(&mut self, _dt: f32, out: &mut Vec<f32>) {
+            out.append(&mut self.captured.borrow_mut());
+        }
+
+        fn error(&self) -> Option<String> {
+            self.error.borrow().clone()
+        }
+    }
+
+    impl Drop for Microphone {
+        fn drop(&mut self) {
+            _ = self.context.close();
+        }
+    }
+}
+
+#[test]
+fn test_sources() {
+    let mut out = Vec::new();
+    let mut tone = Tone::new(250.0, 0.5, 1000.0);
+    // 16⅔ samples a frame at 60 Hz, the fractions add up.
+    for _ in 0..60 {
+        tone.read(1.0 / 60.0, &mut out);
+    }
+    assert_eq!(out.len(), 1000);
+    assert!((out.iter().cloned().fold(0.0, f32::max) - 0.5).abs() < 1e-3);
+
+    // WAV files of every supported kind decode to the same samples.
+    let wav = |tag: u16, bits: u16, channels: u16, data: &[u8]| {
+        let mut bytes = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+        bytes.extend(16u32.to_le_bytes());
+        bytes.extend(tag.to_le_bytes());
+        bytes.extend(channels.to_le_bytes());
+        bytes.extend(8000u32.to_le_bytes());
+        bytes.extend((8000 * channels as u32 * bits as u32 / 8).to_le_bytes());
+        bytes.extend((channels * bits / 8).to_le_bytes());
+        bytes.extend(bits.to_le_bytes());
+        bytes.extend(b"data");
+        bytes.extend((data.len() as u32).to_le_bytes());
+        bytes.extend(data);
+        Wav::decode(&bytes)
+    };
+    let expected = Wav {
+        sample_rate: 8000.0,
+        samples: vec![0.5, -0.5],
+    };
+    assert_eq!(wav(1, 8, 1, &[192, 64]), Ok(expected.clone()));
+    assert_eq!(wav(1, 16, 1, &[0, 0x40, 0, 0xc0]), Ok(expected.clone()));
+    let floats: Vec<u8> = [0.5f32, -0.5]
+        .iter()
+        .flat_map(|x| x.to_le_bytes())
+        .collect();
+    assert_eq!(wav(3, 32, 1, &floats), Ok(expected.clone()));
+    // Stereo is mixed down.
+    assert_eq!(
+        wav(1, 16, 2, &[0, 0x40, 0, 0x40, 0, 0xc0, 0, 0])
+            .unwrap()
+            .samples,
+        [0.5, -0.25]
+    );
+    assert!(wav(2, 4, 1, &[]).is_err());
+    assert!(Wav::decode(b"RIFF").is_err());
+
+    let beat = Wav::beat();
+    assert_eq!(beat.sample_rate, 22050.0);
+    assert_eq!(beat.samples.len(), 2 * 22050);
+    let mut beat_loop = WavLoop::new(beat);
+    out.clear();
+    beat_loop.read(3.0, &mut out);
+    assert_eq!(out.len(), 3 * 22050);
+    assert_eq!(out[2 * 22050..], out[..22050]);
+}
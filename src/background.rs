@@ -0,0 +1,1869 @@
+//! The animated particle background drawn behind every window.
+
+use eframe::egui;
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+
+use crate::audio_reactive::Bands;
+use crate::background_config::{BackgroundConfig, BoundaryMode, PointerTool, SimulationMode};
+use crate::background_mesh;
+use crate::pointer::Pointer;
+
+/// Length of one physics step in seconds, the simulation always advances by whole steps.
+const FIXED_DT: f32 = 1.0 / 60.0;
+
+/// Side in pixels of the square [`BackgroundConfig::point_density`] counts points in. About a
+/// third of a desktop screen's height, so a density gives as many points as it used to when it
+/// counted per 100 pixels of width.
+pub const DENSITY_SQUARE: f32 = 300.0;
+
+/// Points spawned by a click with [`PointerTool::Burst`].
+const BURST_SIZE: usize = 20;
+
+/// How strongly boids steer by each rule at a weight of 1.0, as velocity change per second
+/// per point of distance towards their neighbours' center, per unit of velocity difference to
+/// their neighbours' heading, and per crowding neighbour.
+const COHESION_RATE: f32 = 0.05;
+const ALIGNMENT_RATE: f32 = 1.0;
+const SEPARATION_RATE: f32 = 30.0;
+
+/// Droplets thrown up by a raindrop hitting something.
+const SPLASH_SIZE: usize = 4;
+
+/// Raindrops are drawn as streaks as long as the distance they fall in this many seconds.
+const STREAK_SECONDS: f32 = 0.03;
+
+/// Extra gravity at the loudest bass, see [`Background::set_audio`].
+const BASS_GRAVITY: f32 = 6.0;
+
+/// Opacity of the links while the mids are silent.
+const QUIET_LINK_OPACITY: f32 = 0.2;
+
+/// Loudness of the highs that spawns a burst when they rise past it.
+const HIGHS_BURST_LEVEL: f32 = 0.5;
+
+/// Longest frame we catch up on, so a stalled tab doesn't run hundreds of steps at once.
+const MAX_FRAME_DT: f32 = 0.25;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Point {
+    x: f32,
+    y: f32,
+    xv: f32,
+    yv: f32,
+    radius: f32,
+    mass: f32,
+
+    /// Fraction of the approaching speed kept after a collision, 1.0 is perfectly elastic.
+    restitution: f32,
+
+    /// Seconds until a splash droplet disappears, infinite for every other point.
+    lifetime: f32,
+}
+
+impl Point {
+    fn distance(p1: &Point, p2: &Point) -> f32 {
+        ((p2.x - p1.x).powi(2) + (p2.y - p1.y).powi(2)).sqrt()
+    }
+
+    #[cfg(test)]
+    fn momentum(&self) -> egui::Vec2 {
+        egui::vec2(self.xv, self.yv) * self.mass
+    }
+
+    #[cfg(test)]
+    fn kinetic_energy(&self) -> f32 {
+        0.5 * self.mass * (self.xv * self.xv + self.yv * self.yv)
+    }
+
+    /// Collide two overlapping discs: push them apart so they just touch, moving the lighter one
+    /// further, then exchange an impulse along the line between their centers.
+    ///
+    /// Momentum is conserved, kinetic energy too when both restitutions are 1.0.
+    fn collide(p1: &mut Point, p2: &mut Point) {
+        let delta = egui::vec2(p2.x - p1.x, p2.y - p1.y);
+        let dist = Point::distance(p1, p2);
+        let touching = p1.radius + p2.radius;
+        if dist >= touching {
+            return;
+        }
+        // Points exactly on top of each other get pushed apart sideways.
+        let normal = if dist > 0.0 {
+            delta / dist
+        } else {
+            egui::Vec2::X
+        };
+        let (inv_mass1, inv_mass2) = (1.0 / p1.mass, 1.0 / p2.mass);
+        let inv_mass_sum = inv_mass1 + inv_mass2;
+
+        // Positional correction, so overlapping points don't stick together.
+        let correction = normal * (touching - dist) / inv_mass_sum;
+        p1.x -= correction.x * inv_mass1;
+        p1.y -= correction.y * inv_mass1;
+        p2.x += correction.x * inv_mass2;
+        p2.y += correction.y * inv_mass2;
+
+        let approach = (p2.xv - p1.xv) * normal.x + (p2.yv - p1.yv) * normal.y;
+        if approach >= 0.0 {
+            // Already moving apart.
+            return;
+        }
+        let restitution = p1.restitution.min(p2.restitution);
+        let impulse = normal * (-(1.0 + restitution) * approach / inv_mass_sum);
+        p1.xv -= impulse.x * inv_mass1;
+        p1.yv -= impulse.y * inv_mass1;
+        p2.xv += impulse.x * inv_mass2;
+        p2.yv += impulse.y * inv_mass2;
+    }
+}
+
+/// How [`Background::calculate_collisions`] looks for pairs of close points, only switchable
+/// for tests and the benchmark.
+#[cfg(any(test, feature = "bench"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NeighborSearch {
+    /// Only the neighbouring cells of a uniform grid, so just the pairs that are actually close.
+    #[default]
+    Grid,
+
+    /// The sort and sweep on x this used to be, finding every pair that is close on x. Drawing
+    /// and colliding skip the ones too far apart on y, kept to compare against in the benchmark.
+    /// Wrapping always uses the grid.
+    SweepX,
+}
+
+/// Only the [`BackgroundConfig`] is persisted, the simulation starts fresh every time.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Background {
+    pub config: BackgroundConfig,
+
+    #[serde(skip)]
+    points: Vec<Point>,
+    #[serde(skip)]
+    collisions: Vec<Link>,
+    #[serde(skip)]
+    grid: SpatialGrid,
+    #[cfg(any(test, feature = "bench"))]
+    #[serde(skip)]
+    neighbor_search: NeighborSearch,
+
+    /// The points followed by their images across the edges, for finding links when wrapping.
+    #[serde(skip)]
+    ghosts: Vec<Point>,
+
+    /// Which point `ghosts[points.len() + i]` is an image of, and how far it is moved.
+    #[serde(skip)]
+    ghost_of: Vec<(usize, egui::Vec2)>,
+
+    /// The points before the last step, drawing interpolates from these towards `points`.
+    #[serde(skip)]
+    previous: Vec<Point>,
+
+    /// Simulation time not yet covered by a step, always less than [`FIXED_DT`].
+    #[serde(skip)]
+    accumulator: f32,
+
+    /// Screen the last step ran on.
+    #[serde(skip)]
+    screen_size: egui::Rect,
+
+    /// Rects of the windows on top of the background, see [`Self::set_obstacles`].
+    #[serde(skip)]
+    obstacles: Vec<egui::Rect>,
+
+    /// Texture of [`background_mesh::disc_image`], loaded when first drawing a mesh.
+    #[serde(skip)]
+    disc_texture: Option<egui::TextureHandle>,
+
+    #[serde(skip)]
+    render_times: RenderTimes,
+
+    /// Fraction of the configured points and link distance in use, see [`Self::set_quality`].
+    #[serde(skip)]
+    quality: f32,
+
+    /// Replaces the configured colors, see [`Self::set_contrast_color`].
+    #[serde(skip)]
+    contrast_color: Option<egui::Color32>,
+
+    /// The sound the background reacts to, see [`Self::set_audio`].
+    #[serde(skip)]
+    audio: Option<Bands>,
+
+    /// What every point sees of its neighbours, reused by [`Self::flock`] every step.
+    #[serde(skip)]
+    flock_sums: Vec<FlockSums>,
+
+    /// Source of all randomness in the simulation, so a seed reproduces a run exactly.
+    #[serde(skip)]
+    rng: ChaCha20Rng,
+    #[serde(skip)]
+    seed: u64,
+}
+
+impl Default for Background {
+    /// Seeded with [`requested_seed`] if there is one, randomly otherwise.
+    fn default() -> Self {
+        Self::with_seed(requested_seed().unwrap_or_else(rand::random))
+    }
+}
+
+impl Background {
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            config: BackgroundConfig::default(),
+            points: Vec::new(),
+            collisions: Vec::new(),
+            grid: SpatialGrid::default(),
+            #[cfg(any(test, feature = "bench"))]
+            neighbor_search: NeighborSearch::default(),
+            ghosts: Vec::new(),
+            ghost_of: Vec::new(),
+            obstacles: Vec::new(),
+            disc_texture: None,
+            render_times: RenderTimes::default(),
+            quality: 1.0,
+            contrast_color: None,
+            audio: None,
+            flock_sums: Vec::new(),
+            previous: Vec::new(),
+            accumulator: 0.0,
+            screen_size: egui::Rect::NOTHING,
+            rng: ChaCha20Rng::seed_from_u64(seed),
+            seed,
+        }
+    }
+
+    /// The seed this simulation was created with, pass it back to [`Self::with_seed`] to replay it.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn has_points(&self) -> bool {
+        !self.points.is_empty()
+    }
+
+    /// Fill the screen with points, the number of points scales with the screen area.
+    pub fn add_points(&mut self, screen_size: egui::Rect) {
+        self.fill(screen_size, self.target_point_count(screen_size));
+    }
+
+    /// How many points [`BackgroundConfig::point_density`] asks for on this screen, at the
+    /// current quality.
+    fn target_point_count(&self, screen_size: egui::Rect) -> usize {
+        if !screen_size.is_positive() {
+            return 0;
+        }
+        let squares = screen_size.area() / (DENSITY_SQUARE * DENSITY_SQUARE);
+        (squares * self.config.point_density * self.quality) as usize
+    }
+
+    /// [`BackgroundConfig::link_distance`] at the current quality.
+    fn link_distance(&self) -> f32 {
+        // Fewer points spread further apart, so links don't shrink as fast as the point count.
+        self.config.link_distance * self.quality.sqrt()
+    }
+
+    /// Draw the points and links in `color` instead of the configured colors, with links at
+    /// full strength, or in the configured colors again for `None`.
+    pub fn set_contrast_color(&mut self, color: Option<egui::Color32>) {
+        self.contrast_color = color;
+    }
+
+    /// Run with a `quality` fraction of the configured points and link distance, for keeping
+    /// slow devices smooth.
+    pub fn set_quality(&mut self, quality: f32) {
+        let quality = quality.clamp(0.0, 1.0);
+        if quality != self.quality {
+            self.quality = quality;
+            self.match_density();
+        }
+    }
+
+    /// Find links and touching points with `search`, [`NeighborSearch::Grid`] unless comparing.
+    #[cfg(any(test, feature = "bench"))]
+    pub fn set_neighbor_search(&mut self, search: NeighborSearch) {
+        self.neighbor_search = search;
+    }
+
+    /// React to the loudness of a sound, or stop reacting for `None`: bass adds to gravity,
+    /// mids brighten the links, and highs rising past [`HIGHS_BURST_LEVEL`] spawn a burst.
+    ///
+    /// Boids don't feel gravity, so they miss the bass.
+    pub fn set_audio(&mut self, bands: Option<Bands>) {
+        let was_quiet = self.audio.map_or(true, |old| old.highs < HIGHS_BURST_LEVEL);
+        if let Some(bands) = bands {
+            if was_quiet && bands.highs >= HIGHS_BURST_LEVEL && self.screen_size.is_positive() {
+                let pos = egui::pos2(
+                    self.rng.gen_range(0.0..self.screen_size.width()),
+                    self.rng.gen_range(0.0..self.screen_size.height()),
+                );
+                self.burst(pos);
+            }
+        }
+        self.audio = bands;
+    }
+
+    /// Gravity with the bass added.
+    fn gravity(&self) -> f32 {
+        self.config.gravity + self.audio.map_or(0.0, |bands| bands.bass * BASS_GRAVITY)
+    }
+
+    /// How strongly links are drawn, following the mids.
+    fn link_opacity(&self) -> f32 {
+        self.audio.map_or(1.0, |bands| {
+            QUIET_LINK_OPACITY + (1.0 - QUIET_LINK_OPACITY) * bands.mids
+        })
+    }
+
+    /// Replace the points with `num_points` randomly placed points.
+    pub fn fill(&mut self, screen_size: egui::Rect, num_points: usize) {
+        self.points.clear();
+        self.screen_size = screen_size;
+        self.add_random_points(num_points);
+    }
+
+    fn add_random_points(&mut self, num_points: usize) {
+        for _ in 0..num_points {
+            self.points
+                .push(random_point(&mut self.rng, self.screen_size, &self.config));
+        }
+        self.previous.clone_from(&self.points);
+        self.collisions.clear();
+    }
+
+    /// Switch to `config`, updating the existing points to match it.
+    ///
+    /// Points are scaled to the new [`BackgroundConfig::point_radius`], while a new
+    /// [`BackgroundConfig::radius_spread`] only applies to points created afterwards.
+    pub fn set_config(&mut self, config: BackgroundConfig) {
+        let old = std::mem::replace(&mut self.config, config);
+        let scale = self.config.point_radius / old.point_radius;
+        for point in self.points.iter_mut().chain(self.previous.iter_mut()) {
+            point.radius *= scale;
+            point.mass = mass_of(point.radius);
+            point.restitution = self.config.restitution;
+        }
+        if self.config.mode != SimulationMode::Rain {
+            // Splashes only fade while it rains.
+            self.points.retain(|point| point.lifetime.is_infinite());
+            self.previous.clone_from(&self.points);
+        }
+        self.match_density();
+    }
+
+    /// Fit the points to a new screen size: positions are scaled along with the screen, then
+    /// points are added or removed to keep the density.
+    pub fn resize(&mut self, screen_size: egui::Rect) {
+        let old = self.screen_size;
+        self.screen_size = screen_size;
+        if !old.is_positive() || !screen_size.is_positive() {
+            return;
+        }
+
+        let scale = screen_size.size() / old.size();
+        for point in self.points.iter_mut().chain(self.previous.iter_mut()) {
+            point.x *= scale.x;
+            point.y *= scale.y;
+        }
+        self.match_density();
+    }
+
+    /// Add or remove random points until there are as many as [`BackgroundConfig::point_density`] asks for.
+    pub fn match_density(&mut self) {
+        let target = self.target_point_count(self.screen_size);
+        let len = self.points.len();
+        if target >= len {
+            self.add_random_points(target - len);
+            return;
+        }
+        for _ in target..len {
+            let i = self.rng.gen_range(0..self.points.len());
+            self.points.swap_remove(i);
+        }
+        self.previous.clone_from(&self.points);
+        self.collisions.clear();
+    }
+
+    /// Set the rects points bounce off and link to, usually the visible windows.
+    pub fn set_obstacles(&mut self, obstacles: impl IntoIterator<Item = egui::Rect>) {
+        self.obstacles.clear();
+        self.obstacles.extend(obstacles);
+    }
+
+    /// Advance the simulation by `dt` seconds of real time.
+    ///
+    /// The physics always runs in steps of [`FIXED_DT`], the remainder is carried over to the
+    /// next frame, so the result doesn't depend on the frame rate.
+    pub fn update(&mut self, dt: f32, pointers: &[Pointer], screen_size: egui::Rect) {
+        self.accumulator += dt.clamp(0.0, MAX_FRAME_DT);
+
+        // The tolerance keeps float rounding from dropping a step, e.g. 1/30 s is two steps.
+        let mut steps = 0;
+        while self.accumulator >= FIXED_DT - 1e-6 {
+            steps += 1;
+            self.accumulator -= FIXED_DT;
+        }
+        self.step(steps, pointers, screen_size);
+    }
+
+    /// Advance the simulation by `n` physics steps, independent of any frame timing.
+    ///
+    /// A `screen_size` different from the last step [resizes](Self::resize) the simulation first,
+    /// and [pressed](Pointer::pressed) pointers spawn their burst before the first step.
+    ///
+    /// Nothing happens on an empty screen, e.g. a minimized window, the points wait for it to
+    /// come back.
+    pub fn step(&mut self, n: usize, pointers: &[Pointer], screen_size: egui::Rect) {
+        if !screen_size.is_positive() {
+            return;
+        }
+        if screen_size != self.screen_size {
+            self.resize(screen_size);
+        }
+        if self.config.pointer_tool == PointerTool::Burst {
+            for pointer in pointers.iter().filter(|pointer| pointer.pressed) {
+                self.burst(pointer.pos);
+            }
+        }
+        for _ in 0..n {
+            self.previous.clone_from(&self.points);
+            if self.config.mode == SimulationMode::Rain {
+                // Drops neither link nor collide.
+                self.update_rain(pointers, screen_size, FIXED_DT);
+                self.collisions.clear();
+                continue;
+            }
+            if self.config.mode == SimulationMode::Boids {
+                self.flock(FIXED_DT);
+            }
+            self.update_points(pointers, screen_size, FIXED_DT);
+            self.calculate_collisions();
+            self.resolve_collisions();
+        }
+    }
+
+    /// Steer every point by the rules of a flock: keep some distance from the points it is
+    /// linked to, fly in their direction, and towards their center.
+    ///
+    /// The links found at the end of the last step are the neighbours.
+    fn flock(&mut self, dt: f32) {
+        let boids = &self.config.boids;
+        let view_distance = self.link_distance();
+
+        self.flock_sums.clear();
+        self.flock_sums
+            .resize(self.points.len(), FlockSums::default());
+        for link in &self.collisions {
+            let (a, b) = (&self.points[link.a], &self.points[link.b]);
+            let offset = egui::vec2(b.x + link.offset.x - a.x, b.y + link.offset.y - a.y);
+            let distance = offset.length();
+            if distance >= view_distance || distance == 0.0 {
+                continue;
+            }
+            let crowding = (1.0 - distance / boids.separation_distance).max(0.0);
+            for (i, towards, velocity) in [
+                (link.a, offset, egui::vec2(b.xv, b.yv)),
+                (link.b, -offset, egui::vec2(a.xv, a.yv)),
+            ] {
+                let sums = &mut self.flock_sums[i];
+                sums.count += 1;
+                sums.center += towards;
+                sums.velocity += velocity;
+                sums.separation -= towards / distance * crowding;
+            }
+        }
+
+        for (point, sums) in self.points.iter_mut().zip(&self.flock_sums) {
+            let mut velocity = egui::vec2(point.xv, point.yv);
+            if sums.count > 0 {
+                let count = sums.count as f32;
+                let steer = sums.center / count * boids.cohesion * COHESION_RATE
+                    + (sums.velocity / count - velocity) * boids.alignment * ALIGNMENT_RATE
+                    + sums.separation * boids.separation * SEPARATION_RATE;
+                velocity += steer * dt;
+            }
+
+            let speed = velocity.length();
+            let clamped = speed.clamp(boids.max_speed / 2.0, boids.max_speed);
+            velocity = if speed > 0.0 {
+                velocity * (clamped / speed)
+            } else {
+                egui::Vec2::X * clamped
+            };
+            (point.xv, point.yv) = velocity.into();
+        }
+    }
+
+    /// Spawn [`BURST_SIZE`] points flying out of `pos`.
+    ///
+    /// Beyond twice the density asked for, random points make room for them.
+    pub fn burst(&mut self, pos: egui::Pos2) {
+        for _ in 0..BURST_SIZE {
+            let angle = self.rng.gen_range(0.0..std::f32::consts::TAU);
+            let speed = self.rng.gen_range(10.0..40.0);
+            let radius = random_radius(&mut self.rng, &self.config);
+            let point = Point {
+                x: pos.x,
+                y: pos.y,
+                xv: angle.cos() * speed,
+                yv: angle.sin() * speed,
+                radius,
+                mass: mass_of(radius),
+                restitution: self.config.restitution,
+                lifetime: f32::INFINITY,
+            };
+            self.points.push(point);
+            self.previous.push(point);
+        }
+
+        let max = 2 * self.target_point_count(self.screen_size).max(BURST_SIZE);
+        while self.points.len() > max {
+            let i = self.rng.gen_range(0..self.points.len());
+            self.points.swap_remove(i);
+            self.previous.swap_remove(i);
+        }
+        self.collisions.clear();
+    }
+
+    /// Let the drops fall, splashing on the bottom of the screen and on top of the windows
+    /// before falling again from above the screen, and let the splashes fade.
+    fn update_rain(&mut self, pointers: &[Pointer], screen_size: egui::Rect, dt: f32) {
+        if !screen_size.is_positive() {
+            return;
+        }
+        let gravity = self.gravity();
+        let config = &self.config;
+        let rng = &mut self.rng;
+        let mut splashes = Vec::new();
+        for (point, previous) in self.points.iter_mut().zip(&mut self.previous) {
+            point.x += point.xv * dt * 10.;
+            point.y += point.yv * dt * 10.;
+            point.yv += gravity * dt * 10.;
+            for pointer in pointers {
+                apply_pointer(point, pointer, config);
+            }
+            point.xv *= config.damping;
+            point.yv *= config.damping;
+            point.x = point.x.rem_euclid(screen_size.width());
+
+            if point.lifetime.is_finite() {
+                point.lifetime -= dt;
+                continue;
+            }
+            let pos = egui::pos2(point.x, point.y);
+            let window_top = self
+                .obstacles
+                .iter()
+                .filter(|_| config.collide_with_windows)
+                .find(|obstacle| obstacle.contains(pos))
+                .map(|obstacle| obstacle.min.y);
+            let ground = (point.y >= screen_size.height()).then_some(screen_size.height());
+            if let Some(surface) = window_top.or(ground) {
+                splashes.push((egui::pos2(point.x, surface), *point));
+                *point = Point {
+                    y: rng.gen_range(-50.0..0.0),
+                    ..random_point(rng, screen_size, config)
+                };
+                *previous = *point;
+            }
+        }
+
+        let mut i = 0;
+        while i < self.points.len() {
+            let point = &self.points[i];
+            if point.lifetime <= 0.0 || point.y > screen_size.height() {
+                self.points.swap_remove(i);
+                self.previous.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        for (pos, drop) in splashes {
+            self.splash(pos, &drop);
+        }
+    }
+
+    /// Throw up [`SPLASH_SIZE`] droplets where `drop` hit something at `pos`.
+    fn splash(&mut self, pos: egui::Pos2, drop: &Point) {
+        let speed = drop.yv.abs().sqrt();
+        for _ in 0..SPLASH_SIZE {
+            let radius = drop.radius * 0.6;
+            let droplet = Point {
+                x: pos.x,
+                y: pos.y - 1.0,
+                xv: self.rng.gen_range(-0.5..0.5) * speed + drop.xv * 0.2,
+                yv: -self.rng.gen_range(0.5..1.0) * speed,
+                radius,
+                mass: mass_of(radius),
+                restitution: drop.restitution,
+                lifetime: self.rng.gen_range(0.2..0.5),
+            };
+            self.points.push(droplet);
+            self.previous.push(droplet);
+        }
+    }
+
+    fn update_points(&mut self, pointers: &[Pointer], screen_size: egui::Rect, dt: f32) {
+        if !screen_size.is_positive() {
+            return;
+        }
+        let gravity = self.gravity();
+        let config = &self.config;
+        let rng = &mut self.rng;
+        for (point, previous) in self.points.iter_mut().zip(&mut self.previous) {
+            // Update point velocity and position
+
+            point.x += point.xv * dt * 10.;
+            point.y += point.yv * dt * 10.;
+
+            //point.xv += 9.8 * dt * 10.;
+            if config.mode == SimulationMode::Particles {
+                point.yv += gravity * dt * 10.;
+            }
+
+            for pointer in pointers {
+                apply_pointer(point, pointer, config);
+            }
+
+            point.xv *= config.damping;
+            point.yv *= config.damping;
+
+            let (width, height) = (screen_size.width(), screen_size.height());
+            match config.boundary {
+                BoundaryMode::Wrap => {
+                    point.x = point.x.rem_euclid(width);
+                    point.y = point.y.rem_euclid(height);
+                }
+                BoundaryMode::Reflect => {
+                    (point.x, point.xv) = reflect(point.x, point.xv, width);
+                    (point.y, point.yv) = reflect(point.y, point.yv, height);
+                }
+                BoundaryMode::Absorb => {
+                    if !(0.0..width).contains(&point.x) || !(0.0..height).contains(&point.y) {
+                        *point = random_point(rng, screen_size, config);
+                        // Appear at the new place instead of sliding there.
+                        *previous = *point;
+                    }
+                }
+            }
+
+            if config.collide_with_windows {
+                for obstacle in &self.obstacles {
+                    bounce_off(point, obstacle.expand(point.radius));
+                }
+            }
+        }
+    }
+
+    /// Find every pair of points closer than the [link distance](Self::link_distance), these get
+    /// linked by a line, and every pair of points that touch.
+    ///
+    /// When wrapping, distances are measured around the edges of the screen.
+    pub fn calculate_collisions(&mut self) {
+        self.collisions.clear();
+        if self.config.boundary == BoundaryMode::Wrap && self.screen_size.is_positive() {
+            self.calculate_wrapped_collisions();
+            return;
+        }
+
+        let link_distance = self.search_distance();
+        #[cfg(any(test, feature = "bench"))]
+        if self.neighbor_search == NeighborSearch::SweepX {
+            self.sweep_x(link_distance);
+            return;
+        }
+        self.grid.rebuild(&self.points, link_distance);
+
+        let collisions = &mut self.collisions;
+        self.grid
+            .for_each_pair_within(&self.points, link_distance, |a, b| {
+                collisions.push(Link {
+                    a,
+                    b,
+                    offset: egui::Vec2::ZERO,
+                });
+            });
+    }
+
+    /// Sort by x and keep every pair within `distance` on x, however far apart they are on y.
+    #[cfg(any(test, feature = "bench"))]
+    fn sweep_x(&mut self, distance: f32) {
+        let points = &self.points;
+        let mut sorted: Vec<usize> = (0..points.len()).collect();
+        sorted.sort_by(|&a, &b| points[a].x.total_cmp(&points[b].x));
+
+        let mut active: Vec<usize> = Vec::new();
+        for &i in &sorted {
+            active.retain(|&j| points[j].x + distance >= points[i].x);
+            self.collisions.extend(active.iter().map(|&j| Link {
+                a: j,
+                b: i,
+                offset: egui::Vec2::ZERO,
+            }));
+            active.push(i);
+        }
+    }
+
+    /// Pairs closer than this are either linked or touching.
+    fn search_distance(&self) -> f32 {
+        let max_radius = self.points.iter().fold(0.0, |max, p| p.radius.max(max));
+        self.link_distance().max(2.0 * max_radius)
+    }
+
+    /// Points close to an edge get a ghost image just past the opposite edge, then pairs
+    /// between a point and a ghost are links across the edge.
+    fn calculate_wrapped_collisions(&mut self) {
+        let size = self.screen_size.size();
+        // Keeps the closest image of every point unique.
+        let link_distance = self.search_distance().min(size.x / 2.0).min(size.y / 2.0);
+
+        self.ghosts.clone_from(&self.points);
+        self.ghost_of.clear();
+        let towards_inside = |pos: f32, max: f32| {
+            if pos < link_distance {
+                max
+            } else if pos > max - link_distance {
+                -max
+            } else {
+                0.0
+            }
+        };
+        for (i, p) in self.points.iter().enumerate() {
+            let (dx, dy) = (towards_inside(p.x, size.x), towards_inside(p.y, size.y));
+            // Near a corner there is also a diagonal image.
+            let corner = (dx != 0.0 && dy != 0.0).then_some(egui::vec2(dx, dy));
+            let offsets = [egui::vec2(dx, 0.0), egui::vec2(0.0, dy)]
+                .into_iter()
+                .filter(|offset| *offset != egui::Vec2::ZERO)
+                .chain(corner);
+            for offset in offsets {
+                self.ghosts.push(Point {
+                    x: p.x + offset.x,
+                    y: p.y + offset.y,
+                    ..*p
+                });
+                self.ghost_of.push((i, offset));
+            }
+        }
+
+        self.grid.rebuild(&self.ghosts, link_distance);
+        let n = self.points.len();
+        let (ghost_of, collisions) = (&self.ghost_of, &mut self.collisions);
+        self.grid
+            .for_each_pair_within(&self.ghosts, link_distance, |i, j| {
+                // Pairs of two ghosts are also found between their originals or other ghosts.
+                let (a, ghost) = match (i < n, j < n) {
+                    (true, true) => {
+                        collisions.push(Link {
+                            a: i,
+                            b: j,
+                            offset: egui::Vec2::ZERO,
+                        });
+                        return;
+                    }
+                    (true, false) => (i, j),
+                    (false, true) => (j, i),
+                    (false, false) => return,
+                };
+                // Every link across an edge is found from both sides, keep one of them.
+                let (b, offset) = ghost_of[ghost - n];
+                if a < b {
+                    collisions.push(Link { a, b, offset });
+                }
+            });
+    }
+
+    /// Number of point pairs found by the last [`Self::calculate_collisions`].
+    #[cfg(any(test, feature = "bench"))]
+    pub fn link_count(&self) -> usize {
+        self.collisions.len()
+    }
+
+    /// Positions of every point.
+    #[cfg(any(test, feature = "bench"))]
+    pub fn positions(&self) -> impl Iterator<Item = egui::Pos2> + '_ {
+        self.points.iter().map(|p| egui::Pos2::new(p.x, p.y))
+    }
+
+    /// Bounce apart the points that touch.
+    fn resolve_collisions(&mut self) {
+        for link in &self.collisions {
+            let (point, other) = Background::get_two_mut(&mut self.points, link.a, link.b);
+            let mut image = Point {
+                x: other.x + link.offset.x,
+                y: other.y + link.offset.y,
+                ..*other
+            };
+            Point::collide(point, &mut image);
+            *other = Point {
+                x: image.x - link.offset.x,
+                y: image.y - link.offset.y,
+                ..image
+            };
+        }
+    }
+
+    /// Where point `i` is drawn: between its position before and after the last step, by
+    /// how far we are into the next step.
+    fn interpolated_position(&self, i: usize) -> egui::Pos2 {
+        let current = self.points[i];
+        let current = egui::Pos2::new(current.x, current.y);
+        let Some(previous) = self.previous.get(i) else {
+            return current;
+        };
+        let previous = egui::Pos2::new(previous.x, previous.y);
+
+        // A point that jumped across half the screen in one step wrapped around it.
+        let jump = (current - previous).abs();
+        if jump.x > self.screen_size.width() / 2.0 || jump.y > self.screen_size.height() / 2.0 {
+            return current;
+        }
+        previous.lerp(current, self.accumulator / FIXED_DT)
+    }
+
+    fn get_two_mut(slice: &mut [Point], idx1: usize, idx2: usize) -> (&mut Point, &mut Point) {
+        assert!(idx1 != idx2, "Indices must be distinct");
+        let (first, second) = if idx1 < idx2 {
+            (idx1, idx2)
+        } else {
+            (idx2, idx1)
+        };
+        let (head, tail) = slice.split_at_mut(second);
+        (&mut head[first], &mut tail[0])
+    }
+
+    // Returns data for egui to draw, not drawing directly
+    fn prepare_draw_data(&self) -> Vec<DrawCommand> {
+        let mut commands = Vec::new();
+        let point_color = self.contrast_color.unwrap_or(self.config.point_color);
+        let link_color = self
+            .contrast_color
+            .unwrap_or(self.config.link_color)
+            .gamma_multiply(self.link_opacity());
+        let positions: Vec<egui::Pos2> = (0..self.points.len())
+            .map(|i| self.interpolated_position(i))
+            .collect();
+
+        for (&center, point) in positions.iter().zip(&self.points) {
+            if self.config.mode == SimulationMode::Rain && point.lifetime.is_infinite() {
+                let fall = egui::vec2(point.xv, point.yv) * 10.0 * STREAK_SECONDS;
+                commands.push(DrawCommand::Line {
+                    points: [center - fall, center],
+                    width: point.radius,
+                    color: point_color,
+                });
+                continue;
+            }
+            commands.push(DrawCommand::Circle {
+                center,
+                radius: point.radius,
+                color: point_color,
+            });
+        }
+
+        for link in &self.collisions {
+            let (point, other) = (positions[link.a], positions[link.b] + link.offset);
+            let dist = point.distance(other);
+
+            let link_distance = self.link_distance();
+            if dist >= link_distance {
+                // Only found because the points touch.
+                continue;
+            }
+            let opacity = (link_distance - dist) / link_distance;
+            let color = link_color.gamma_multiply(opacity);
+            commands.push(DrawCommand::Line {
+                points: [point, other],
+                width: 0.5,
+                color,
+            });
+            if link.offset != egui::Vec2::ZERO {
+                // The same link leaving through the opposite edge.
+                commands.push(DrawCommand::Line {
+                    points: [point - link.offset, positions[link.b]],
+                    width: 0.5,
+                    color,
+                });
+            }
+        }
+
+        if self.config.link_window_corners {
+            let link_distance = self.link_distance();
+            let corners = self.obstacles.iter().flat_map(|rect| {
+                [
+                    rect.left_top(),
+                    rect.right_top(),
+                    rect.left_bottom(),
+                    rect.right_bottom(),
+                ]
+            });
+            for corner in corners {
+                for &point in &positions {
+                    let dist = point.distance(corner);
+                    if dist < link_distance {
+                        let opacity = (link_distance - dist) / link_distance;
+                        commands.push(DrawCommand::Line {
+                            points: [corner, point],
+                            width: 0.5,
+                            color: link_color.gamma_multiply(opacity),
+                        });
+                    }
+                }
+            }
+        }
+
+        commands
+    }
+
+    /// Draw the points and links, as one mesh if [`BackgroundConfig::batch_mesh`] is set.
+    pub fn render_draw_data(&mut self, painter: egui::Painter) {
+        self.render_times.drawn_as_mesh = Some(self.config.batch_mesh);
+        let start = now();
+        let commands = self.prepare_draw_data();
+
+        let (times, size) = if self.config.batch_mesh {
+            let disc = self.disc_texture.get_or_insert_with(|| {
+                painter.ctx().load_texture(
+                    "background_disc",
+                    background_mesh::disc_image(),
+                    egui::TextureOptions::LINEAR,
+                )
+            });
+            let mesh = background_mesh::build_mesh(&commands, disc.id());
+            let vertices = mesh.vertices.len();
+            painter.add(egui::Shape::mesh(mesh));
+            (&mut self.render_times.mesh, vertices)
+        } else {
+            let shapes = commands.len();
+            for command in commands {
+                match command {
+                    DrawCommand::Circle {
+                        center,
+                        radius,
+                        color,
+                    } => {
+                        painter.circle_filled(center, radius, color);
+                    }
+                    DrawCommand::Line {
+                        points,
+                        width,
+                        color,
+                    } => {
+                        painter.line_segment(points, (width, color));
+                    }
+                }
+            }
+            (&mut self.render_times.shapes, shapes)
+        };
+        average(&mut times.build, (now() - start) as f32);
+        times.size = size;
+    }
+
+    /// Add the CPU time of the last frame, from [`eframe::Frame::info`], to the frame times
+    /// of the render path that frame was drawn with, so call it before drawing this frame.
+    pub fn record_frame_time(&mut self, cpu_usage: Option<f32>) {
+        let times = match self.render_times.drawn_as_mesh {
+            Some(true) => &mut self.render_times.mesh,
+            Some(false) => &mut self.render_times.shapes,
+            None => return,
+        };
+        if let Some(cpu_usage) = cpu_usage {
+            average(&mut times.frame, cpu_usage);
+        }
+    }
+
+    /// A small table in the corner comparing the cost of both render paths, with a switch
+    /// between them.
+    pub fn frame_time_overlay(&mut self, ctx: &egui::Context) {
+        let ms = |seconds: Option<f32>| {
+            seconds.map_or_else(|| "–".to_owned(), |s| format!("{:.2} ms", s * 1000.0))
+        };
+        // In front of the windows, and not a window the points would bounce off.
+        egui::Area::new("background_frame_times")
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    egui::Grid::new("background_frame_times_grid")
+                        .num_columns(4)
+                        .show(ui, |ui| {
+                            ui.label("");
+                            ui.strong("Build");
+                            ui.strong("Frame");
+                            ui.strong("Size");
+                            ui.end_row();
+
+                            let render_times = &self.render_times;
+                            for (batch, name, times, unit) in [
+                                (false, "Shapes", render_times.shapes, "shapes"),
+                                (true, "Mesh", render_times.mesh, "vertices"),
+                            ] {
+                                ui.radio_value(&mut self.config.batch_mesh, batch, name);
+                                ui.label(ms(times.build));
+                                ui.label(ms(times.frame));
+                                ui.label(format!("{} {unit}", times.size));
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+    }
+}
+
+/// Sums over the neighbours of a point, relative to the point.
+#[derive(Clone, Copy, Default)]
+struct FlockSums {
+    count: u32,
+    center: egui::Vec2,
+    velocity: egui::Vec2,
+    separation: egui::Vec2,
+}
+
+/// Recent cost of drawing the background with each render path.
+#[derive(Default)]
+struct RenderTimes {
+    shapes: PathTimes,
+    mesh: PathTimes,
+
+    /// Whether the last frame was drawn as a mesh, `None` before the first one. Switching
+    /// paths doesn't change which path the time of that frame belongs to.
+    drawn_as_mesh: Option<bool>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct PathTimes {
+    /// Seconds spent building the shapes or the mesh.
+    build: Option<f32>,
+
+    /// Seconds of CPU time of a whole frame.
+    frame: Option<f32>,
+
+    /// Number of shapes or vertices drawn last time.
+    size: usize,
+}
+
+/// Fold `sample` into a moving average over roughly the last 20 samples.
+fn average(average: &mut Option<f32>, sample: f32) {
+    *average = Some(average.map_or(sample, |average| average * 0.95 + sample * 0.05));
+}
+
+/// Seconds since an arbitrary start, for timing parts of a frame.
+fn now() -> f64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+        START
+            .get_or_init(std::time::Instant::now)
+            .elapsed()
+            .as_secs_f64()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    web_sys::window()
+        .and_then(|window| window.performance())
+        .map_or(0.0, |performance| performance.now() / 1000.0)
+}
+
+/// Two points close enough to be linked.
+struct Link {
+    a: usize,
+    b: usize,
+
+    /// Added to the position of `b` to get its image closest to `a`, non-zero for links
+    /// across the edges of the screen.
+    offset: egui::Vec2,
+}
+
+fn random_point(
+    rng: &mut ChaCha20Rng,
+    screen_size: egui::Rect,
+    config: &BackgroundConfig,
+) -> Point {
+    let x = rng.gen_range(0.0..screen_size.width());
+    let y = rng.gen_range(0.0..screen_size.height());
+    let xv = rng.gen_range(-20.0..=20.0);
+    let yv: f32 = rng.gen_range(-20.0..=20.0);
+    let radius = random_radius(rng, config);
+    let (xv, yv) = if config.mode == SimulationMode::Rain {
+        // Drops start out falling straight down.
+        (0.0, 20.0 + yv.abs())
+    } else {
+        (xv, yv)
+    };
+    Point {
+        x,
+        y,
+        xv,
+        yv,
+        radius,
+        mass: mass_of(radius),
+        restitution: config.restitution,
+        lifetime: f32::INFINITY,
+    }
+}
+
+/// [`BackgroundConfig::point_radius`] give or take the [`BackgroundConfig::radius_spread`].
+fn random_radius(rng: &mut ChaCha20Rng, config: &BackgroundConfig) -> f32 {
+    let spread = config.radius_spread;
+    config.point_radius * (1.0 + rng.gen_range(-spread..=spread))
+}
+
+/// Points are flat discs of equal density.
+fn mass_of(radius: f32) -> f32 {
+    radius * radius
+}
+
+/// What [`BackgroundConfig::pointer_tool`] does to a point near `pointer`.
+fn apply_pointer(point: &mut Point, pointer: &Pointer, config: &BackgroundConfig) {
+    let offset = egui::pos2(point.x, point.y) - pointer.pos;
+    if offset.length() >= config.mouse_radius {
+        return;
+    }
+    let away = offset.normalized();
+    // Points stay in range of the pulling tools instead of getting pushed out, so they pull gently.
+    let gentle = config.mouse_force * 0.1;
+    let push = match config.pointer_tool {
+        PointerTool::Repel => away * config.mouse_force,
+        PointerTool::Attract => -away * gentle,
+        PointerTool::Vortex => away.rot90() * gentle,
+        PointerTool::Throw => {
+            if pointer.down {
+                // Positions advance by ten times the velocity per second.
+                (point.xv, point.yv) = (pointer.velocity / 10.0).into();
+            }
+            return;
+        }
+        PointerTool::Burst => return,
+    };
+    point.xv += push.x;
+    point.yv += push.y;
+}
+
+/// Push a point inside `rect` out through the closest side, moving away from it.
+fn bounce_off(point: &mut Point, rect: egui::Rect) {
+    if !rect.contains(egui::pos2(point.x, point.y)) {
+        return;
+    }
+    let left = point.x - rect.min.x;
+    let right = rect.max.x - point.x;
+    let top = point.y - rect.min.y;
+    let bottom = rect.max.y - point.y;
+    let closest = left.min(right).min(top).min(bottom);
+    if closest == left {
+        point.x = rect.min.x;
+        point.xv = -point.xv.abs();
+    } else if closest == right {
+        point.x = rect.max.x;
+        point.xv = point.xv.abs();
+    } else if closest == top {
+        point.y = rect.min.y;
+        point.yv = -point.yv.abs();
+    } else {
+        point.y = rect.max.y;
+        point.yv = point.yv.abs();
+    }
+}
+
+/// Mirror a coordinate that left `0..=max` back inside, turning its velocity around.
+fn reflect(pos: f32, velocity: f32, max: f32) -> (f32, f32) {
+    if pos < 0.0 {
+        ((-pos).min(max), velocity.abs())
+    } else if pos > max {
+        ((2.0 * max - pos).max(0.0), -velocity.abs())
+    } else {
+        (pos, velocity)
+    }
+}
+
+pub enum DrawCommand {
+    Circle {
+        center: egui::Pos2,
+        radius: f32,
+        color: egui::Color32,
+    },
+    Line {
+        points: [egui::Pos2; 2],
+        width: f32,
+        color: egui::Color32,
+    },
+}
+
+/// The seed requested with `--seed <n>` on the command line, or `?seed=<n>` in the URL on the web.
+pub fn requested_seed() -> Option<u64> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let value = {
+        let args: Vec<String> = std::env::args().collect();
+        args.iter().enumerate().find_map(|(i, arg)| {
+            if arg == "--seed" {
+                args.get(i + 1).cloned()
+            } else {
+                arg.strip_prefix("--seed=").map(str::to_owned)
+            }
+        })
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    let value = web_sys::window()
+        .and_then(|w| w.location().search().ok())
+        .and_then(|search| {
+            search
+                .trim_start_matches('?')
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("seed=").map(str::to_owned))
+        });
+
+    let value = value?;
+    match value.parse() {
+        Ok(seed) => Some(seed),
+        Err(err) => {
+            log::warn!("Ignoring seed {value:?}: {err}");
+            None
+        }
+    }
+}
+
+/// Uniform grid of square cells bucketing point indices, for finding nearby points without
+/// comparing every pair.
+#[derive(Default)]
+struct SpatialGrid {
+    min: egui::Pos2,
+    cell_size: f32,
+    cols: usize,
+    rows: usize,
+
+    /// `cell_points[cell_start[c]..cell_start[c + 1]]` are the indices of the points in cell `c`.
+    cell_start: Vec<usize>,
+    cell_points: Vec<usize>,
+}
+
+impl SpatialGrid {
+    /// Bucket `points` into cells at least `min_cell_size` wide.
+    fn rebuild(&mut self, points: &[Point], min_cell_size: f32) {
+        let (mut min, mut max) = (egui::Pos2::ZERO, egui::Pos2::ZERO);
+        if let Some(first) = points.first() {
+            min = egui::Pos2::new(first.x, first.y);
+            max = min;
+        }
+        for p in points {
+            min = min.min(egui::Pos2::new(p.x, p.y));
+            max = max.max(egui::Pos2::new(p.x, p.y));
+        }
+
+        // Cells must be at least as big as the search radius, but sparse points spread over a
+        // large area shouldn't make us allocate mostly empty cells either.
+        let max_cells = 4 * points.len() + 64;
+        self.min = min;
+        self.cell_size = min_cell_size.max(1.0);
+        loop {
+            self.cols = ((max.x - min.x) / self.cell_size) as usize + 1;
+            self.rows = ((max.y - min.y) / self.cell_size) as usize + 1;
+            if self.cols.saturating_mul(self.rows) <= max_cells {
+                break;
+            }
+            self.cell_size *= 2.0;
+        }
+
+        // Counting sort of the point indices by cell.
+        self.cell_start.clear();
+        self.cell_start.resize(self.cols * self.rows + 1, 0);
+        for p in points {
+            let cell = self.cell_of(p);
+            self.cell_start[cell + 1] += 1;
+        }
+        for c in 1..self.cell_start.len() {
+            self.cell_start[c] += self.cell_start[c - 1];
+        }
+        self.cell_points.clear();
+        self.cell_points.resize(points.len(), 0);
+        let mut next = self.cell_start.clone();
+        for (i, p) in points.iter().enumerate() {
+            let cell = self.cell_of(p);
+            self.cell_points[next[cell]] = i;
+            next[cell] += 1;
+        }
+    }
+
+    fn cell_of(&self, p: &Point) -> usize {
+        let col = (((p.x - self.min.x) / self.cell_size) as usize).min(self.cols - 1);
+        let row = (((p.y - self.min.y) / self.cell_size) as usize).min(self.rows - 1);
+        row * self.cols + col
+    }
+
+    fn cell(&self, col: usize, row: usize) -> &[usize] {
+        let c = row * self.cols + col;
+        &self.cell_points[self.cell_start[c]..self.cell_start[c + 1]]
+    }
+
+    /// Call `f(i, j)` once for every pair of points at most `radius` apart.
+    ///
+    /// `radius` must not be larger than the cell size the grid was built with.
+    fn for_each_pair_within(&self, points: &[Point], radius: f32, mut f: impl FnMut(usize, usize)) {
+        debug_assert!(radius <= self.cell_size);
+        let radius_sq = radius * radius;
+        let mut check = |i: usize, j: usize| {
+            let (a, b) = (&points[i], &points[j]);
+            if (a.x - b.x).powi(2) + (a.y - b.y).powi(2) <= radius_sq {
+                f(i, j);
+            }
+        };
+
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                let cell = self.cell(col, row);
+                for (n, &i) in cell.iter().enumerate() {
+                    for &j in &cell[n + 1..] {
+                        check(i, j);
+                    }
+                }
+
+                // Only look at the neighbours ahead of us, the others already looked at this cell.
+                let right = col + 1 < self.cols;
+                let down = row + 1 < self.rows;
+                let mut neighbours = [None; 4];
+                if right {
+                    neighbours[0] = Some(self.cell(col + 1, row));
+                }
+                if down {
+                    neighbours[1] = Some(self.cell(col, row + 1));
+                    if col > 0 {
+                        neighbours[2] = Some(self.cell(col - 1, row + 1));
+                    }
+                    if right {
+                        neighbours[3] = Some(self.cell(col + 1, row + 1));
+                    }
+                }
+                for other in neighbours.into_iter().flatten() {
+                    for &i in cell {
+                        for &j in other {
+                            check(i, j);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_grid_finds_exactly_the_near_pairs() {
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(1000.0, 2000.0));
+    for boundary in BoundaryMode::ALL {
+        let mut background = Background::with_seed(0);
+        background.config.boundary = boundary;
+        background.fill(screen, 500);
+        background.calculate_collisions();
+
+        let mut found: Vec<_> = background
+            .collisions
+            .iter()
+            .map(|link| (link.a.min(link.b), link.a.max(link.b)))
+            .collect();
+        found.sort_unstable();
+
+        // Around the edges when wrapping, straight across the screen otherwise.
+        let distance = |p: &Point, q: &Point| {
+            let (mut dx, mut dy) = ((p.x - q.x).abs(), (p.y - q.y).abs());
+            if boundary == BoundaryMode::Wrap {
+                dx = dx.min(screen.width() - dx);
+                dy = dy.min(screen.height() - dy);
+            }
+            (dx * dx + dy * dy).sqrt()
+        };
+        let points = &background.points;
+        let mut expected = Vec::new();
+        for i in 0..points.len() {
+            for j in i + 1..points.len() {
+                if distance(&points[i], &points[j]) <= background.config.link_distance {
+                    expected.push((i, j));
+                }
+            }
+        }
+        assert_eq!(found, expected, "{boundary:?}");
+
+        // The old sweep finds more pairs, but draws the same links.
+        if boundary != BoundaryMode::Wrap {
+            let lines = |background: &Background| {
+                background
+                    .prepare_draw_data()
+                    .iter()
+                    .filter(|command| matches!(command, DrawCommand::Line { .. }))
+                    .count()
+            };
+            let grid_lines = lines(&background);
+            background.set_neighbor_search(NeighborSearch::SweepX);
+            background.calculate_collisions();
+            assert!(background.link_count() > found.len(), "{boundary:?}");
+            assert_eq!(lines(&background), grid_lines, "{boundary:?}");
+        }
+    }
+}
+
+#[test]
+fn test_empty_screen_pauses() {
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(400.0, 300.0));
+    let minimized = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::Vec2::ZERO);
+    for boundary in BoundaryMode::ALL {
+        let mut background = Background::with_seed(5);
+        background.config.boundary = boundary;
+        background.fill(screen, 50);
+        let before = background.points.clone();
+        for empty in [minimized, egui::Rect::NOTHING] {
+            background.step(10, &[], empty);
+            assert_eq!(background.points, before, "{boundary:?}");
+        }
+
+        // Coming back to the same screen carries on where it stopped.
+        background.step(10, &[], screen);
+        assert!(
+            background
+                .positions()
+                .all(|p| p.x.is_finite() && p.y.is_finite()),
+            "{boundary:?}"
+        );
+        assert_eq!(background.points.len(), before.len(), "{boundary:?}");
+    }
+}
+
+#[test]
+fn test_boundary_modes_keep_points_on_screen() {
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(400.0, 300.0));
+    for boundary in BoundaryMode::ALL {
+        let mut background = Background::with_seed(3);
+        background.config.boundary = boundary;
+        background.config.gravity = 5.0;
+        background.fill(screen, 100);
+        background.step(600, &[Pointer::at(egui::pos2(200.0, 150.0))], screen);
+        assert!(
+            background
+                .positions()
+                .all(|p| (0.0..=400.0).contains(&p.x) && (0.0..=300.0).contains(&p.y)),
+            "{boundary:?}"
+        );
+    }
+
+    assert_eq!(reflect(-3.0, -2.0, 10.0), (3.0, 2.0));
+    assert_eq!(reflect(12.0, 2.0, 10.0), (8.0, -2.0));
+    assert_eq!(reflect(5.0, 2.0, 10.0), (5.0, 2.0));
+}
+
+#[test]
+fn test_simulation_does_not_depend_on_frame_rate() {
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0));
+    let mut reference = Background::with_seed(1);
+    reference.fill(screen, 200);
+
+    // One second of simulation at 30, 60 and 144 frames per second.
+    let run = |fps: u32| {
+        let mut background = Background::default();
+        background.fill(screen, 0);
+        background.points = reference.points.clone();
+        for _ in 0..fps {
+            background.update(1.0 / fps as f32, &[], screen);
+        }
+        background
+    };
+    let (slow, normal, fast) = (run(30), run(60), run(144));
+    assert_eq!(slow.points, normal.points);
+    assert_eq!(fast.points, normal.points);
+}
+
+/// Replays a fixed input sequence and compares the final points with a stored snapshot.
+///
+/// After an intended change to the physics, run with `UPDATE_SNAPSHOTS=1` to accept the new result.
+#[test]
+fn test_replay_matches_snapshot() {
+    let replay = || {
+        let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0));
+        let mut background = Background::with_seed(42);
+        background.fill(screen, 50);
+
+        // The pointer sweeps across the middle of the screen, then rests in the corner.
+        for i in 0..32 {
+            background.step(
+                10,
+                &[Pointer::at(egui::pos2(i as f32 * 25.0, 300.0))],
+                screen,
+            );
+        }
+        background.step(100, &[Pointer::at(egui::Pos2::ZERO)], screen);
+
+        background
+            .points
+            .iter()
+            .map(|p| format!("{:?} {:?} {:?} {:?}\n", p.x, p.y, p.xv, p.yv))
+            .collect::<String>()
+    };
+
+    let snapshot = replay();
+    assert_eq!(
+        snapshot,
+        replay(),
+        "same seed and inputs must replay the same"
+    );
+
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/snapshots/background_replay.txt"
+    );
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(path, &snapshot).unwrap();
+    }
+    let expected = std::fs::read_to_string(path).unwrap_or_default();
+    assert!(
+        snapshot == expected,
+        "points differ from {path}, run with UPDATE_SNAPSHOTS=1 if that is intended"
+    );
+}
+
+#[test]
+fn test_resize_keeps_relative_positions_and_density() {
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0));
+    let mut background = Background::with_seed(7);
+    // A whole number of points per square, so the counts below don't round.
+    background.set_config(BackgroundConfig {
+        point_density: 20.0,
+        ..Default::default()
+    });
+    background.add_points(screen);
+    let before = background.points.clone();
+
+    // Half the height and half the area: half the points stay and just move.
+    let shorter = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 300.0));
+    background.resize(shorter);
+    assert_eq!(background.points.len(), before.len() / 2);
+    for new in &background.points {
+        assert!(before
+            .iter()
+            .any(|old| (new.x, new.y) == (old.x, old.y * 0.5)));
+    }
+
+    // Rotating a phone: narrower and taller, the same area and so as many points.
+    let landscape = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(900.0, 400.0));
+    background.step(1, &[], landscape);
+    let landscape_count = background.points.len();
+    let portrait = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(400.0, 900.0));
+    background.step(1, &[], portrait);
+    assert_eq!(background.points.len(), landscape_count);
+    assert!(background
+        .positions()
+        .all(|p| portrait.contains(p) || p.x == 400.0 || p.y == 900.0));
+
+    // Four times the area, four times the points: the density stays.
+    let wide = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(1600.0, 900.0));
+    background.step(1, &[], wide);
+    let density = |count: usize, screen: egui::Rect| count as f32 / screen.area();
+    assert_eq!(
+        density(background.points.len(), wide),
+        density(landscape_count, landscape)
+    );
+    assert_eq!(
+        background.points.len() as f32,
+        background.config.point_density * wide.area() / (DENSITY_SQUARE * DENSITY_SQUARE)
+    );
+}
+
+#[test]
+fn test_points_bounce_off_obstacles() {
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0));
+    let window = egui::Rect::from_min_size(egui::pos2(300.0, 200.0), egui::vec2(200.0, 200.0));
+    let mut background = Background::with_seed(0);
+    background.config.gravity = 0.0;
+    background.config.damping = 1.0;
+    background.fill(screen, 0);
+    background.set_obstacles([window]);
+
+    // Heading right, straight into the left side of the window.
+    background.points = vec![Point {
+        x: 280.0,
+        y: 300.0,
+        xv: 30.0,
+        yv: 0.0,
+        ..random_point(&mut background.rng, screen, &background.config)
+    }];
+    background.step(30, &[], screen);
+    let point = background.points[0];
+    assert!(point.x <= window.min.x, "{point:?}");
+    assert!(point.xv < 0.0, "{point:?}");
+
+    background.config.link_window_corners = true;
+    background.points = vec![Point {
+        x: 290.0,
+        y: 190.0,
+        xv: 0.0,
+        yv: 0.0,
+        ..random_point(&mut background.rng, screen, &background.config)
+    }];
+    background.previous.clone_from(&background.points);
+    let corner_links = background
+        .prepare_draw_data()
+        .iter()
+        .filter(|command| matches!(command, DrawCommand::Line { .. }))
+        .count();
+    assert_eq!(corner_links, 1);
+
+    background.config.collide_with_windows = false;
+    background.points[0].x = 400.0;
+    background.points[0].y = 300.0;
+    background.step(1, &[], screen);
+    assert!(window.contains(background.positions().next().unwrap()));
+}
+
+#[test]
+fn test_collisions_conserve_momentum_and_energy() {
+    let mut rng = ChaCha20Rng::seed_from_u64(5);
+    let config = BackgroundConfig::default();
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(20.0, 20.0));
+    for restitution in [1.0, 0.5, 0.0] {
+        for _ in 0..1000 {
+            let mut random = || Point {
+                restitution,
+                ..random_point(&mut rng, screen, &config)
+            };
+            let (mut a, mut b) = (random(), random());
+            let momentum = a.momentum() + b.momentum();
+            let energy = a.kinetic_energy() + b.kinetic_energy();
+
+            Point::collide(&mut a, &mut b);
+
+            let momentum_after = a.momentum() + b.momentum();
+            assert!(
+                (momentum_after - momentum).length() <= 1e-3 * momentum.length().max(1.0),
+                "{momentum:?} became {momentum_after:?}"
+            );
+            let energy_after = a.kinetic_energy() + b.kinetic_energy();
+            if restitution == 1.0 {
+                assert!((energy_after - energy).abs() <= 1e-3 * energy);
+            } else {
+                assert!(energy_after <= energy * (1.0 + 1e-4));
+            }
+            // Never left overlapping.
+            assert!(Point::distance(&a, &b) >= (a.radius + b.radius) * (1.0 - 1e-4));
+        }
+    }
+
+    // A head-on hit between equal points swaps their velocities.
+    let still = Point {
+        x: 0.0,
+        y: 0.0,
+        xv: 0.0,
+        yv: 0.0,
+        radius: 5.0,
+        mass: mass_of(5.0),
+        restitution: 1.0,
+        lifetime: f32::INFINITY,
+    };
+    let mut moving = Point {
+        x: 9.0,
+        xv: -10.0,
+        ..still
+    };
+    let mut still = still;
+    Point::collide(&mut still, &mut moving);
+    assert_eq!((still.xv, moving.xv), (-10.0, 0.0));
+    assert_eq!(moving.x - still.x, 10.0);
+}
+
+#[test]
+fn test_pointer_tools() {
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0));
+    let center = egui::pos2(400.0, 300.0);
+    let run = |tool: PointerTool, pointers: &[Pointer]| {
+        let mut background = Background::with_seed(9);
+        background.config.pointer_tool = tool;
+        background.config.gravity = 0.0;
+        background.config.damping = 1.0;
+        background.fill(screen, 0);
+        background.points = vec![Point {
+            x: 430.0,
+            y: 300.0,
+            xv: 0.0,
+            yv: 0.0,
+            ..random_point(&mut background.rng, screen, &background.config)
+        }];
+        background.step(1, pointers, screen);
+        background
+    };
+    let hover = [Pointer::at(center)];
+
+    assert!(run(PointerTool::Repel, &hover).points[0].xv > 0.0);
+    assert!(run(PointerTool::Attract, &hover).points[0].xv < 0.0);
+    let vortex = run(PointerTool::Vortex, &hover).points[0];
+    assert!(vortex.yv.abs() > vortex.xv.abs());
+
+    // Without a pointer nothing pushes the point, not even from the corner.
+    assert_eq!(run(PointerTool::Repel, &[]).points[0].xv, 0.0);
+
+    let drag = Pointer {
+        pos: center,
+        velocity: egui::vec2(0.0, -500.0),
+        down: true,
+        pressed: false,
+    };
+    assert_eq!(run(PointerTool::Throw, &[drag]).points[0].yv, -50.0);
+    assert_eq!(run(PointerTool::Throw, &hover).points[0].yv, 0.0);
+
+    let click = Pointer {
+        pressed: true,
+        ..drag
+    };
+    assert_eq!(
+        run(PointerTool::Burst, &[click]).points.len(),
+        1 + BURST_SIZE
+    );
+}
+
+#[test]
+fn test_boids_line_up() {
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0));
+    let mut background = Background::with_seed(11);
+    background.config.mode = SimulationMode::Boids;
+    background.fill(screen, 150);
+
+    // 1.0 when every point flies the same way, near 0.0 when they go every which way.
+    let order = |background: &Background| {
+        let velocities = background.points.iter().map(|p| egui::vec2(p.xv, p.yv));
+        let sum = velocities.clone().fold(egui::Vec2::ZERO, |sum, v| sum + v);
+        sum.length() / velocities.map(|v| v.length()).sum::<f32>()
+    };
+    assert!(order(&background) < 0.2);
+
+    background.step(60 * 20, &[], screen);
+    assert!(order(&background) > 0.5, "{}", order(&background));
+    let max_speed = background.config.boids.max_speed;
+    let too_fast = background
+        .points
+        .iter()
+        .filter(|p| egui::vec2(p.xv, p.yv).length() > max_speed * 1.01)
+        .count();
+    assert!(too_fast < 10, "{too_fast} points too fast");
+}
+
+#[test]
+fn test_rain_splashes_on_windows_and_ground() {
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0));
+    let window = egui::Rect::from_min_size(egui::pos2(300.0, 200.0), egui::vec2(200.0, 200.0));
+    let mut background = Background::with_seed(13);
+    background.set_config(BackgroundConfig::rain());
+    background.set_obstacles([window]);
+    background.fill(screen, 200);
+
+    let mut splashed = false;
+    for _ in 0..300 {
+        background.step(1, &[], screen);
+        let (drops, splashes): (Vec<Point>, Vec<Point>) = background
+            .points
+            .iter()
+            .partition(|point| point.lifetime.is_infinite());
+        assert_eq!(drops.len(), 200);
+        // Drops never fall through a window.
+        assert!(drops
+            .iter()
+            .all(|drop| !window.contains(egui::pos2(drop.x, drop.y))));
+        splashed |= !splashes.is_empty();
+    }
+    assert!(splashed);
+    assert_eq!(background.link_count(), 0);
+    assert!(background
+        .prepare_draw_data()
+        .iter()
+        .any(|command| matches!(command, DrawCommand::Line { .. })));
+
+    // Splashes don't outlive the rain.
+    background.set_config(BackgroundConfig::default());
+    assert!(background
+        .points
+        .iter()
+        .all(|point| point.lifetime.is_infinite()));
+
+    // Nowhere to fall on an empty screen, and bursts don't need one.
+    background.set_config(BackgroundConfig::rain());
+    let before = background.points.clone();
+    background.update_rain(&[], egui::Rect::NOTHING, FIXED_DT);
+    assert_eq!(background.points, before);
+    let mut fresh = Background::with_seed(13);
+    fresh.burst(egui::pos2(10.0, 10.0));
+    assert_eq!(fresh.points.len(), BURST_SIZE);
+}
+
+#[test]
+fn test_audio_moves_background() {
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0));
+    let run = |bands: Option<Bands>| {
+        let mut background = Background::with_seed(17);
+        background.fill(screen, 100);
+        background.set_audio(bands);
+        background.step(30, &[], screen);
+        background
+    };
+    let fall = |background: &Background| background.points.iter().map(|p| p.yv).sum::<f32>();
+    let quiet = run(Some(Bands::default()));
+    assert_eq!(quiet.points.len(), 100);
+
+    let bass = run(Some(Bands {
+        bass: 1.0,
+        ..Default::default()
+    }));
+    assert!(fall(&bass) > fall(&quiet) + 100.0);
+
+    // Highs burst once as they get loud, not for as long as they stay loud.
+    let mut highs = run(Some(Bands {
+        highs: 1.0,
+        ..Default::default()
+    }));
+    assert_eq!(highs.points.len(), 100 + BURST_SIZE);
+    highs.set_audio(Some(Bands {
+        highs: 0.9,
+        ..Default::default()
+    }));
+    assert_eq!(highs.points.len(), 100 + BURST_SIZE);
+
+    // Links fade while the mids are quiet.
+    let link_alpha = |background: &Background| {
+        background
+            .prepare_draw_data()
+            .iter()
+            .filter_map(|command| match command {
+                DrawCommand::Line { color, .. } => Some(color.a() as u32),
+                DrawCommand::Circle { .. } => None,
+            })
+            .sum::<u32>()
+    };
+    let mut mids = run(Some(Bands {
+        mids: 1.0,
+        ..Default::default()
+    }));
+    let loud = link_alpha(&mids);
+    mids.set_audio(None);
+    assert_eq!(link_alpha(&mids), loud);
+    assert!(link_alpha(&quiet) < loud / 2);
+}
+
+#[test]
+fn test_frame_time_goes_to_the_path_drawn() {
+    let ctx = egui::Context::default();
+    let mut background = Background::with_seed(1);
+    let draw = |background: &mut Background| {
+        let _ = ctx.run(Default::default(), |ctx| {
+            background.render_draw_data(ctx.layer_painter(egui::LayerId::background()));
+        });
+    };
+
+    // Nothing drawn yet, so no path to blame.
+    background.record_frame_time(Some(0.01));
+    assert!(background.render_times.shapes.frame.is_none());
+    assert!(background.render_times.mesh.frame.is_none());
+
+    background.config.batch_mesh = false;
+    draw(&mut background);
+    // Switched to the mesh, but the frame being timed was still drawn as shapes.
+    background.config.batch_mesh = true;
+    background.record_frame_time(Some(0.01));
+    assert_eq!(background.render_times.shapes.frame, Some(0.01));
+    assert!(background.render_times.mesh.frame.is_none());
+
+    draw(&mut background);
+    background.record_frame_time(Some(0.002));
+    assert_eq!(background.render_times.mesh.frame, Some(0.002));
+}
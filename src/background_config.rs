@@ -0,0 +1,440 @@
+//! Tunable parameters of the particle [`crate::Background`] and the settings window editing them.
+
+use eframe::egui;
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct BackgroundConfig {
+    /// Number of points per square of [`crate::background::DENSITY_SQUARE`] pixels, so the
+    /// same on every screen size and orientation.
+    pub point_density: f32,
+
+    /// How the points move.
+    pub mode: SimulationMode,
+
+    /// Weights of the flocking rules in [`SimulationMode::Boids`].
+    pub boids: BoidsConfig,
+
+    /// Downwards acceleration.
+    pub gravity: f32,
+
+    /// Fraction of velocity kept every step, 1.0 means no damping.
+    pub damping: f32,
+
+    /// What the mouse and fingers do to the points.
+    pub pointer_tool: PointerTool,
+
+    /// Points closer than this to a pointer are affected by the [`Self::pointer_tool`].
+    pub mouse_radius: f32,
+
+    /// Velocity added per step to points within [`Self::mouse_radius`] when repelling, the
+    /// other tools scale it down.
+    pub mouse_force: f32,
+
+    /// Points closer than this get linked by a line.
+    pub link_distance: f32,
+
+    /// Average radius of a point, their mass grows with their area.
+    pub point_radius: f32,
+
+    /// How much the radius of new points varies, as a fraction of [`Self::point_radius`].
+    pub radius_spread: f32,
+
+    /// Fraction of the approaching speed points keep when they collide, 1.0 is perfectly elastic.
+    pub restitution: f32,
+
+    pub point_color: egui::Color32,
+
+    /// Color of the shortest links, longer links fade out.
+    pub link_color: egui::Color32,
+
+    /// What happens to points reaching the edge of the screen.
+    pub boundary: BoundaryMode,
+
+    /// Points bounce off the windows drawn on top of the background.
+    pub collide_with_windows: bool,
+
+    /// Link points to the corners of nearby windows too.
+    pub link_window_corners: bool,
+
+    /// Draw everything as one mesh rather than a shape per point and link.
+    pub batch_mesh: bool,
+
+    /// Show how long drawing takes with and without [`Self::batch_mesh`].
+    pub show_frame_times: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum SimulationMode {
+    /// Points fly freely, pulled down by gravity.
+    #[default]
+    Particles,
+
+    /// Points flock together, every point steering by the points it is linked to.
+    Boids,
+
+    /// Points are raindrops, splashing on the bottom of the screen and on top of the windows.
+    Rain,
+}
+
+impl SimulationMode {
+    pub const ALL: [Self; 3] = [Self::Particles, Self::Boids, Self::Rain];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Particles => "Particles",
+            Self::Boids => "Boids",
+            Self::Rain => "Rain",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct BoidsConfig {
+    /// Steering away from neighbours closer than [`Self::separation_distance`].
+    pub separation: f32,
+
+    /// Steering towards the average heading of the neighbours.
+    pub alignment: f32,
+
+    /// Steering towards the center of the neighbours.
+    pub cohesion: f32,
+
+    pub separation_distance: f32,
+
+    /// Boids speed up to at least half of this, and never go faster.
+    pub max_speed: f32,
+}
+
+impl Default for BoidsConfig {
+    fn default() -> Self {
+        Self {
+            separation: 1.5,
+            alignment: 1.0,
+            cohesion: 1.0,
+            separation_distance: 25.0,
+            max_speed: 10.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum BoundaryMode {
+    /// Leave on one side and come back on the other, links reach across the edges too.
+    #[default]
+    Wrap,
+
+    /// Bounce off the edges.
+    Reflect,
+
+    /// Disappear at the edges and respawn somewhere random on the screen.
+    Absorb,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum PointerTool {
+    /// Push points away.
+    #[default]
+    Repel,
+
+    /// Pull points in.
+    Attract,
+
+    /// Swirl points around the pointer.
+    Vortex,
+
+    /// Grab points while the button is held, they keep the speed of the pointer when let go.
+    Throw,
+
+    /// Spawn a burst of points on every click.
+    Burst,
+}
+
+impl PointerTool {
+    pub const ALL: [Self; 5] = [
+        Self::Repel,
+        Self::Attract,
+        Self::Vortex,
+        Self::Throw,
+        Self::Burst,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Repel => "Repel",
+            Self::Attract => "Attract",
+            Self::Vortex => "Vortex",
+            Self::Throw => "Throw",
+            Self::Burst => "Burst",
+        }
+    }
+}
+
+impl BoundaryMode {
+    pub const ALL: [Self; 3] = [Self::Wrap, Self::Reflect, Self::Absorb];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Wrap => "Wrap",
+            Self::Reflect => "Reflect",
+            Self::Absorb => "Absorb",
+        }
+    }
+}
+
+impl Default for BackgroundConfig {
+    fn default() -> Self {
+        Self {
+            point_density: 50.0 / 3.0,
+            mode: SimulationMode::Particles,
+            boids: BoidsConfig::default(),
+            gravity: 0.98,
+            damping: 0.999,
+            pointer_tool: PointerTool::Repel,
+            mouse_radius: 60.0,
+            mouse_force: 30.0,
+            link_distance: 120.0,
+            point_radius: 5.0,
+            radius_spread: 0.4,
+            restitution: 1.0,
+            point_color: egui::Color32::from_gray(200),
+            link_color: egui::Color32::from_rgba_unmultiplied(164, 171, 176, 128),
+            boundary: BoundaryMode::Wrap,
+            collide_with_windows: true,
+            link_window_corners: false,
+            batch_mesh: true,
+            show_frame_times: false,
+        }
+    }
+}
+
+impl BackgroundConfig {
+    /// Named starting points for the settings, the first one is the default.
+    pub fn presets() -> [(&'static str, Self); 6] {
+        let default = Self::default();
+        [
+            ("Default", default.clone()),
+            (
+                "Calm",
+                Self {
+                    point_density: 10.0,
+                    gravity: 0.2,
+                    damping: 0.99,
+                    mouse_force: 10.0,
+                    link_distance: 150.0,
+                    ..default.clone()
+                },
+            ),
+            (
+                "Storm",
+                Self {
+                    point_density: 30.0,
+                    gravity: 4.0,
+                    damping: 0.9995,
+                    pointer_tool: PointerTool::Vortex,
+                    mouse_radius: 100.0,
+                    mouse_force: 60.0,
+                    link_distance: 90.0,
+                    point_radius: 4.0,
+                    radius_spread: 0.6,
+                    restitution: 0.8,
+                    point_color: egui::Color32::from_rgb(170, 190, 230),
+                    link_color: egui::Color32::from_rgba_unmultiplied(120, 150, 220, 128),
+                    boundary: BoundaryMode::Wrap,
+                    collide_with_windows: true,
+                    link_window_corners: true,
+                    ..default.clone()
+                },
+            ),
+            (
+                "Zero-g",
+                Self {
+                    gravity: 0.0,
+                    damping: 1.0,
+                    boundary: BoundaryMode::Reflect,
+                    ..default.clone()
+                },
+            ),
+            (
+                "Flock",
+                Self {
+                    mode: SimulationMode::Boids,
+                    point_density: 8.0,
+                    damping: 1.0,
+                    link_distance: 80.0,
+                    point_radius: 3.0,
+                    radius_spread: 0.0,
+                    link_color: egui::Color32::from_rgba_unmultiplied(164, 171, 176, 64),
+                    ..default
+                },
+            ),
+            ("Rain", Self::rain()),
+        ]
+    }
+
+    /// Thin drops falling fast, see [`SimulationMode::Rain`].
+    pub fn rain() -> Self {
+        Self {
+            mode: SimulationMode::Rain,
+            point_density: 20.0,
+            gravity: 4.0,
+            damping: 0.98,
+            mouse_force: 10.0,
+            point_radius: 1.0,
+            radius_spread: 0.3,
+            point_color: egui::Color32::from_rgba_unmultiplied(170, 190, 230, 160),
+            ..Self::default()
+        }
+    }
+
+    /// Sliders and color pickers for every parameter.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            for (name, preset) in Self::presets() {
+                if ui.selectable_label(*self == preset, name).clicked() {
+                    *self = preset;
+                }
+            }
+        });
+        ui.separator();
+
+        egui::Grid::new("background_settings")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Points per 300×300 px");
+                ui.add(egui::Slider::new(&mut self.point_density, 0.0..=60.0));
+                ui.end_row();
+
+                ui.label("Mode");
+                ui.horizontal(|ui| {
+                    for mode in SimulationMode::ALL {
+                        ui.selectable_value(&mut self.mode, mode, mode.name());
+                    }
+                });
+                ui.end_row();
+
+                if self.mode == SimulationMode::Boids {
+                    let boids = &mut self.boids;
+                    ui.label("Separation");
+                    ui.add(egui::Slider::new(&mut boids.separation, 0.0..=5.0));
+                    ui.end_row();
+
+                    ui.label("Alignment");
+                    ui.add(egui::Slider::new(&mut boids.alignment, 0.0..=5.0));
+                    ui.end_row();
+
+                    ui.label("Cohesion");
+                    ui.add(egui::Slider::new(&mut boids.cohesion, 0.0..=5.0));
+                    ui.end_row();
+
+                    ui.label("Personal space");
+                    ui.add(egui::Slider::new(
+                        &mut boids.separation_distance,
+                        5.0..=100.0,
+                    ));
+                    ui.end_row();
+
+                    ui.label("Max speed");
+                    ui.add(egui::Slider::new(&mut boids.max_speed, 1.0..=40.0));
+                    ui.end_row();
+                } else {
+                    ui.label("Gravity");
+                    ui.add(egui::Slider::new(&mut self.gravity, -5.0..=5.0));
+                    ui.end_row();
+                }
+
+                ui.label("Damping");
+                ui.add(egui::Slider::new(&mut self.damping, 0.95..=1.0).max_decimals(4));
+                ui.end_row();
+
+                ui.label("Pointer tool");
+                ui.horizontal(|ui| {
+                    for tool in PointerTool::ALL {
+                        ui.selectable_value(&mut self.pointer_tool, tool, tool.name());
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Mouse radius");
+                ui.add(egui::Slider::new(&mut self.mouse_radius, 0.0..=200.0));
+                ui.end_row();
+
+                ui.label("Mouse force");
+                ui.add(egui::Slider::new(&mut self.mouse_force, 0.0..=100.0));
+                ui.end_row();
+
+                ui.label("Link distance");
+                ui.add(egui::Slider::new(&mut self.link_distance, 10.0..=250.0));
+                ui.end_row();
+
+                ui.label("Point radius");
+                ui.add(egui::Slider::new(&mut self.point_radius, 1.0..=20.0));
+                ui.end_row();
+
+                ui.label("Radius spread")
+                    .on_hover_text("Applies to new points");
+                ui.add(egui::Slider::new(&mut self.radius_spread, 0.0..=0.9));
+                ui.end_row();
+
+                ui.label("Bounciness");
+                ui.add(egui::Slider::new(&mut self.restitution, 0.0..=1.0));
+                ui.end_row();
+
+                ui.label("Edges");
+                ui.horizontal(|ui| {
+                    for mode in BoundaryMode::ALL {
+                        ui.selectable_value(&mut self.boundary, mode, mode.name());
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Windows");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.collide_with_windows, "Collide");
+                    ui.checkbox(&mut self.link_window_corners, "Link corners");
+                });
+                ui.end_row();
+
+                ui.label("Rendering");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.batch_mesh, "Single mesh");
+                    ui.checkbox(&mut self.show_frame_times, "Frame times");
+                });
+                ui.end_row();
+
+                ui.label("Point color");
+                egui::color_picker::color_edit_button_srgba(
+                    ui,
+                    &mut self.point_color,
+                    egui::color_picker::Alpha::OnlyBlend,
+                );
+                ui.end_row();
+
+                ui.label("Link color");
+                egui::color_picker::color_edit_button_srgba(
+                    ui,
+                    &mut self.link_color,
+                    egui::color_picker::Alpha::OnlyBlend,
+                );
+                ui.end_row();
+            });
+    }
+}
+
+#[test]
+fn test_presets() {
+    let presets = BackgroundConfig::presets();
+    assert_eq!(presets[0].1, BackgroundConfig::default());
+    for (name, preset) in &presets {
+        if *name != "Default" {
+            assert_ne!(*preset, BackgroundConfig::default(), "{name}");
+        }
+        let saved = toml::to_string(preset).unwrap();
+        let loaded: BackgroundConfig = toml::from_str(&saved).unwrap();
+        assert_eq!(loaded, *preset, "{name}");
+    }
+    for name in ["Calm", "Storm", "Zero-g"] {
+        assert!(presets.iter().any(|(n, _)| *n == name), "{name}");
+    }
+}
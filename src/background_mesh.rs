@@ -0,0 +1,128 @@
+//! Draws the whole [`crate::Background`] as a single [`egui::Mesh`] instead of one shape per
+//! point and link, which saves egui from tessellating thousands of shapes every frame.
+
+use eframe::egui;
+
+use crate::background::DrawCommand;
+
+/// Size of the disc texture in texels.
+const DISC_SIZE: usize = 32;
+
+/// Radius of the disc in texels, leaving room for the anti-aliased edge.
+const DISC_RADIUS: f32 = DISC_SIZE as f32 / 2.0 - 1.0;
+
+/// Width of the transparent fringe on both sides of a line, in points.
+const LINE_FEATHER: f32 = 0.5;
+
+/// A white anti-aliased disc, points are quads showing it tinted with their color.
+pub fn disc_image() -> egui::ColorImage {
+    let center = DISC_SIZE as f32 / 2.0;
+    let pixels = (0..DISC_SIZE * DISC_SIZE)
+        .map(|i| {
+            let (x, y) = ((i % DISC_SIZE) as f32 + 0.5, (i / DISC_SIZE) as f32 + 0.5);
+            let distance = egui::vec2(x - center, y - center).length();
+            let coverage = (DISC_RADIUS + 0.5 - distance).clamp(0.0, 1.0);
+            egui::Color32::from_white_alpha((coverage * 255.0).round() as u8)
+        })
+        .collect();
+    egui::ColorImage {
+        size: [DISC_SIZE, DISC_SIZE],
+        pixels,
+    }
+}
+
+/// Build `commands` into one mesh textured with [`disc_image`].
+///
+/// Circles become quads showing the disc, lines become thin quads sampling its opaque center,
+/// fading out to transparent vertices along their sides.
+pub fn build_mesh(commands: &[DrawCommand], disc: egui::TextureId) -> egui::Mesh {
+    let mut mesh = egui::Mesh::with_texture(disc);
+    let full_uv = egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0));
+    let center_uv = egui::pos2(0.5, 0.5);
+    // The disc texture is a little bigger than the disc.
+    let quad_scale = DISC_SIZE as f32 / 2.0 / DISC_RADIUS;
+
+    for command in commands {
+        match *command {
+            DrawCommand::Circle {
+                center,
+                radius,
+                color,
+            } => {
+                let rect = egui::Rect::from_center_size(
+                    center,
+                    egui::Vec2::splat(2.0 * radius * quad_scale),
+                );
+                mesh.add_rect_with_uv(rect, full_uv, color);
+            }
+            DrawCommand::Line {
+                points: [a, b],
+                width,
+                color,
+            } => {
+                if a == b {
+                    continue;
+                }
+                let side = (b - a).normalized().rot90() * (width / 2.0 + LINE_FEATHER);
+
+                // Three rows along the line: a transparent edge, the line itself, another edge.
+                let first = mesh.vertices.len() as u32;
+                for end in [a, b] {
+                    for (pos, color) in [
+                        (end - side, egui::Color32::TRANSPARENT),
+                        (end, color),
+                        (end + side, egui::Color32::TRANSPARENT),
+                    ] {
+                        mesh.vertices.push(egui::epaint::Vertex {
+                            pos,
+                            uv: center_uv,
+                            color,
+                        });
+                    }
+                }
+                for i in [0, 1] {
+                    mesh.add_triangle(first + i, first + i + 1, first + i + 3);
+                    mesh.add_triangle(first + i + 1, first + i + 3, first + i + 4);
+                }
+            }
+        }
+    }
+    mesh
+}
+
+#[test]
+fn test_build_mesh() {
+    let commands = [
+        DrawCommand::Circle {
+            center: egui::pos2(10.0, 10.0),
+            radius: 5.0,
+            color: egui::Color32::WHITE,
+        },
+        DrawCommand::Line {
+            points: [egui::pos2(0.0, 0.0), egui::pos2(10.0, 0.0)],
+            width: 0.5,
+            color: egui::Color32::RED,
+        },
+        // Nothing to draw for a line without length.
+        DrawCommand::Line {
+            points: [egui::pos2(3.0, 3.0); 2],
+            width: 0.5,
+            color: egui::Color32::RED,
+        },
+    ];
+    let mesh = build_mesh(&commands, egui::TextureId::default());
+    assert!(mesh.is_valid());
+    assert_eq!(mesh.vertices.len(), 4 + 6);
+    assert_eq!(mesh.indices.len(), 6 + 12);
+
+    // Only the middle row of the line is visible.
+    let line = &mesh.vertices[4..];
+    let alphas: Vec<u8> = line.iter().map(|v| v.color.a()).collect();
+    assert_eq!(alphas, [0, 255, 0, 0, 255, 0]);
+    assert_eq!(line[0].pos.y, -line[2].pos.y);
+
+    let disc = disc_image();
+    let center = DISC_SIZE / 2 * DISC_SIZE + DISC_SIZE / 2;
+    assert_eq!(disc.pixels[center].a(), 255);
+    assert_eq!(disc.pixels[0].a(), 0);
+}
@@ -0,0 +1,607 @@
+//! Chaos equations in the style of CodeParade: two quadratic equations in `x`, `y` and a time
+//! `t`, iterated from `(t, t)` and plotted, while `t` slowly moves.
+//!
+//! Every term has a coefficient of -1, 0 or 1, which makes 3¹⁸ equations, each named by a
+//! code of six letters. Other equations can be typed in as [`Expression`]s.
+
+use eframe::egui;
+use rand::prelude::*;
+
+use crate::expression::{self, Expression, ParseError};
+
+/// The terms of each equation, in the order of their coefficients and of the codes.
+const TERMS: [&str; 9] = ["x²", "y²", "t²", "xy", "xt", "yt", "x", "y", "t"];
+
+/// The [`TERMS`] as an [`Expression`] writes them.
+const TERM_EXPRESSIONS: [&str; 9] = ["x^2", "y^2", "t^2", "x*y", "x*t", "y*t", "x", "y", "t"];
+
+/// Letters of the codes, each one holds three coefficients as a base 3 number.
+const CODE_LETTERS: &[u8; 27] = b"_ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Times every equation is iterated from its starting point for one value of `t`.
+const ITERATIONS: usize = 500;
+
+/// Values of `t` drawn at once, the oldest fading out, and how far apart they are.
+const TRAIL_STEPS: usize = 12;
+const TRAIL_DT: f32 = 0.0005;
+
+/// Iteration stops once a point is this far out, it only gets further.
+const ESCAPE: f32 = 1e6;
+
+/// The range `t` runs through.
+const T_START: f32 = -3.0;
+const T_END: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChaosEquation {
+    /// Coefficients of the [`TERMS`] of `x'`, then of `y'`.
+    pub params: [i8; 18],
+}
+
+impl ChaosEquation {
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let mut params = [0; 18];
+        for param in &mut params {
+            *param = rng.gen_range(-1..=1);
+        }
+        Self { params }
+    }
+
+    /// Parse a code of six letters or underscores, case doesn't matter. Shorter codes are
+    /// padded with underscores, like CodeParade's are.
+    pub fn from_code(code: &str) -> Result<Self, String> {
+        let code = code.trim().to_ascii_uppercase();
+        let letters = code.chars().count();
+        if !(1..=6).contains(&letters) {
+            return Err(format!(
+                "A code has up to six letters, \"{code}\" has {letters}."
+            ));
+        }
+        let mut params = [-1; 18];
+        for (i, letter) in code.chars().enumerate() {
+            let mut value = CODE_LETTERS
+                .iter()
+                .position(|&l| l as char == letter)
+                .ok_or_else(|| format!("'{letter}' isn't a letter or '_'."))?;
+            // The first coefficient is the most significant digit.
+            for param in params[i * 3..i * 3 + 3].iter_mut().rev() {
+                *param = (value % 3) as i8 - 1;
+                value /= 3;
+            }
+        }
+        Ok(Self { params })
+    }
+
+    pub fn code(&self) -> String {
+        self.params
+            .chunks(3)
+            .map(|digits| {
+                let value = digits
+                    .iter()
+                    .fold(0, |value, &param| value * 3 + (param + 1) as usize);
+                CODE_LETTERS[value] as char
+            })
+            .collect()
+    }
+
+    /// One iteration from `(x, y)` at time `t`.
+    pub fn apply(&self, x: f32, y: f32, t: f32) -> (f32, f32) {
+        let terms = [x * x, y * y, t * t, x * y, x * t, y * t, x, y, t];
+        let sum = |params: &[i8]| {
+            params
+                .iter()
+                .zip(terms)
+                .map(|(&param, term)| param as f32 * term)
+                .sum()
+        };
+        (sum(&self.params[..9]), sum(&self.params[9..]))
+    }
+
+    pub fn orbit(&self, t: f32) -> Vec<egui::Pos2> {
+        orbit(t, |x, y| self.apply(x, y, t))
+    }
+
+    /// `x' = …` and `y' = …` written out.
+    pub fn formulas(&self) -> [String; 2] {
+        [
+            format!("x' = {}", sum(&self.params[..9], TERMS)),
+            format!("y' = {}", sum(&self.params[9..], TERMS)),
+        ]
+    }
+
+    /// The right hand sides of `x'` and `y'` as [`Expression`]s.
+    pub fn expressions(&self) -> [String; 2] {
+        [
+            sum(&self.params[..9], TERM_EXPRESSIONS),
+            sum(&self.params[9..], TERM_EXPRESSIONS),
+        ]
+    }
+}
+
+/// The `terms` with their coefficients written as signs, dropping the zero ones.
+fn sum(params: &[i8], terms: [&str; 9]) -> String {
+    let mut sum = String::new();
+    for (&param, term) in params.iter().zip(terms) {
+        match (param, sum.is_empty()) {
+            (0, _) => continue,
+            (1, true) => {}
+            (-1, true) => sum.push('-'),
+            (1, false) => sum.push_str(" + "),
+            _ => sum.push_str(" - "),
+        }
+        sum.push_str(term);
+    }
+    if sum.is_empty() {
+        sum.push('0');
+    }
+    sum
+}
+
+/// The points visited from `(t, t)` by iterating `apply`, up to where they escape.
+fn orbit(t: f32, apply: impl Fn(f32, f32) -> (f32, f32)) -> Vec<egui::Pos2> {
+    let (mut x, mut y) = (t, t);
+    let mut points = Vec::with_capacity(ITERATIONS);
+    for _ in 0..ITERATIONS {
+        (x, y) = apply(x, y);
+        if !(x.abs() < ESCAPE && y.abs() < ESCAPE) {
+            break;
+        }
+        points.push(egui::pos2(x, y));
+    }
+    points
+}
+
+/// Equations typed in by the user, in `x`, `y` and `t` like the others.
+#[derive(Clone, Debug)]
+pub struct CustomEquation {
+    x: Expression,
+    y: Expression,
+}
+
+impl CustomEquation {
+    /// Parse the right hand sides of `x'` and `y'`, or tell what is wrong with each of them.
+    pub fn parse(texts: &[String; 2]) -> Result<Self, [Option<ParseError>; 2]> {
+        let [x, y] = texts
+            .clone()
+            .map(|text| Expression::parse(&text, &["x", "y", "t"]));
+        match (x, y) {
+            (Ok(x), Ok(y)) => Ok(Self { x, y }),
+            (x, y) => Err([x.err(), y.err()]),
+        }
+    }
+
+    pub fn apply(&self, x: f32, y: f32, t: f32) -> (f32, f32) {
+        let variables = [x, y, t];
+        (self.x.eval(&variables), self.y.eval(&variables))
+    }
+
+    pub fn orbit(&self, t: f32) -> Vec<egui::Pos2> {
+        orbit(t, |x, y| self.apply(x, y, t))
+    }
+}
+
+/// An equation worth coming back to, at a time that looks good.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Favorite {
+    pub code: String,
+    pub t: f32,
+    /// `x'` and `y'` when they were typed in rather than given by the code.
+    #[serde(default)]
+    pub custom: Option<[String; 2]>,
+}
+
+/// The window plotting a [`ChaosEquation`], with controls for time, the equation and a list of
+/// favorites.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ChaosViewer {
+    pub open: bool,
+
+    /// Code of the equation shown.
+    code: String,
+
+    t: f32,
+
+    /// How fast `t` moves while playing, per second.
+    speed: f32,
+
+    /// Half the size of the plot in equation units.
+    zoom: f32,
+
+    favorites: Vec<Favorite>,
+
+    /// Whether the typed in equations are shown rather than the code.
+    custom: bool,
+    custom_text: [String; 2],
+
+    #[serde(skip)]
+    playing: bool,
+
+    /// The code as being typed.
+    #[serde(skip)]
+    code_input: String,
+
+    #[serde(skip)]
+    code_error: Option<String>,
+
+    /// The last typed in equations that parsed, kept on screen while fixing the text.
+    #[serde(skip)]
+    custom_equation: Option<CustomEquation>,
+    #[serde(skip)]
+    custom_errors: [Option<ParseError>; 2],
+}
+
+impl Default for ChaosViewer {
+    fn default() -> Self {
+        let code = "FJG_QB".to_owned();
+        let custom_text = ChaosEquation::from_code(&code)
+            .expect("valid code")
+            .expressions();
+        Self {
+            open: false,
+            code_input: code.clone(),
+            code,
+            t: T_START,
+            speed: 0.01,
+            zoom: 2.0,
+            favorites: Vec::new(),
+            playing: true,
+            code_error: None,
+            custom: false,
+            custom_text,
+            custom_equation: None,
+            custom_errors: [None, None],
+        }
+    }
+}
+
+impl ChaosViewer {
+    fn equation(&self) -> ChaosEquation {
+        ChaosEquation::from_code(&self.code).unwrap_or(ChaosEquation { params: [0; 18] })
+    }
+
+    fn set_equation(&mut self, equation: ChaosEquation) {
+        self.code = equation.code();
+        self.code_input = self.code.clone();
+        self.code_error = None;
+    }
+
+    fn set_custom_text(&mut self, text: [String; 2]) {
+        self.custom_text = text;
+        self.parse_custom();
+    }
+
+    fn parse_custom(&mut self) {
+        match CustomEquation::parse(&self.custom_text) {
+            Ok(equation) => {
+                self.custom_equation = Some(equation);
+                self.custom_errors = [None, None];
+            }
+            Err(errors) => self.custom_errors = errors,
+        }
+    }
+
+    /// The orbit of the equation shown.
+    fn orbit(&self, t: f32) -> Vec<egui::Pos2> {
+        if !self.custom {
+            return self.equation().orbit(t);
+        }
+        self.custom_equation
+            .as_ref()
+            .map_or_else(Vec::new, |equation| equation.orbit(t))
+    }
+
+    /// Move `t` by `dt`, starting over at the other end of the range when leaving it.
+    fn advance(&mut self, dt: f32) {
+        self.t += dt;
+        if self.t > T_END {
+            self.t = T_START;
+        } else if self.t < T_START {
+            self.t = T_END;
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        // The typed in equations are saved as text, parse them after loading.
+        if self.custom && self.custom_equation.is_none() && self.custom_errors == [None, None] {
+            self.parse_custom();
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Chaos equations")
+            .open(&mut open)
+            .auto_sized()
+            .show(ctx, |ui| self.ui(ui));
+        self.open = open;
+
+        if self.open && self.playing {
+            self.advance(self.speed * ctx.input(|input| input.stable_dt.min(0.1)));
+            ctx.request_repaint();
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let play = if self.playing { "⏸" } else { "▶" };
+            if ui.button(play).clicked() {
+                self.playing = !self.playing;
+            }
+            if ui.button("⏮").on_hover_text("Step back").clicked() {
+                self.playing = false;
+                self.advance(-TRAIL_DT);
+            }
+            if ui.button("⏭").on_hover_text("Step forward").clicked() {
+                self.playing = false;
+                self.advance(TRAIL_DT);
+            }
+            ui.add(
+                egui::Slider::new(&mut self.t, T_START..=T_END)
+                    .text("t")
+                    .max_decimals(4),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::Slider::new(&mut self.speed, 0.0001..=0.1)
+                    .logarithmic(true)
+                    .text("speed"),
+            );
+            ui.add(
+                egui::Slider::new(&mut self.zoom, 0.1..=10.0)
+                    .logarithmic(true)
+                    .text("size"),
+            );
+        });
+
+        self.plot(ui);
+
+        if ui
+            .checkbox(&mut self.custom, "Write your own equations")
+            .on_hover_text("Any formula of x, y and t, like x^2 - y*t + sin(t)")
+            .changed()
+            && self.custom
+        {
+            self.set_custom_text(self.equation().expressions());
+        }
+        if self.custom {
+            self.custom_ui(ui);
+        } else {
+            self.code_ui(ui);
+        }
+        ui.collapsing("Favorites", |ui| {
+            if self.favorites.is_empty() {
+                ui.weak("Save an equation to find it here.");
+            }
+            let (mut load, mut remove) = (None, None);
+            for (i, favorite) in self.favorites.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let name = match &favorite.custom {
+                        Some([x, y]) => format!("{x}, {y}"),
+                        None => favorite.code.clone(),
+                    };
+                    if ui
+                        .link(format!("{name} at t = {:.4}", favorite.t))
+                        .clicked()
+                    {
+                        load = Some(i);
+                    }
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = load {
+                let favorite = self.favorites[i].clone();
+                if let Ok(equation) = ChaosEquation::from_code(&favorite.code) {
+                    self.set_equation(equation);
+                }
+                self.custom = favorite.custom.is_some();
+                if let Some(text) = favorite.custom {
+                    self.set_custom_text(text);
+                }
+                self.t = favorite.t;
+                self.playing = false;
+            }
+            if let Some(i) = remove {
+                self.favorites.remove(i);
+            }
+        });
+    }
+
+    fn code_ui(&mut self, ui: &mut egui::Ui) {
+        let mut equation = self.equation();
+        for formula in equation.formulas() {
+            ui.monospace(formula);
+        }
+        ui.horizontal(|ui| {
+            ui.label("Code");
+            let edit = ui.add(egui::TextEdit::singleline(&mut self.code_input).desired_width(70.0));
+            if edit.changed() {
+                match ChaosEquation::from_code(&self.code_input) {
+                    Ok(typed) => {
+                        self.code = typed.code();
+                        self.code_error = None;
+                    }
+                    Err(err) => self.code_error = Some(err),
+                }
+            }
+            self.buttons(ui);
+        });
+        if let Some(err) = &self.code_error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+
+        ui.collapsing("Coefficients", |ui| {
+            let before = equation;
+            egui::Grid::new("chaos_coefficients").show(ui, |ui| {
+                ui.label("");
+                for term in TERMS {
+                    ui.label(term);
+                }
+                ui.end_row();
+                for (name, params) in ["x'", "y'"].into_iter().zip(equation.params.chunks_mut(9)) {
+                    ui.label(name);
+                    for param in params {
+                        // Clicking cycles through -1, 0 and 1.
+                        let label = ["-1", "0", "+1"][(*param + 1) as usize];
+                        if ui.small_button(label).clicked() {
+                            *param = (*param + 2) % 3 - 1;
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+            if equation != before {
+                self.set_equation(equation);
+            }
+        });
+    }
+
+    fn custom_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        egui::Grid::new("chaos_custom")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (i, name) in ["x' =", "y' ="].into_iter().enumerate() {
+                    ui.monospace(name);
+                    let error = self.custom_errors[i].as_ref();
+                    let edit =
+                        expression::expression_edit(ui, &mut self.custom_text[i], error, 300.0);
+                    changed |= edit.changed();
+                    ui.end_row();
+                }
+            });
+        if changed {
+            self.parse_custom();
+        }
+        for (name, (error, text)) in ["x'", "y'"]
+            .into_iter()
+            .zip(self.custom_errors.iter().zip(&self.custom_text))
+        {
+            if let Some(error) = error {
+                let message = format!("{name}, column {}: {}", error.column(text), error.message);
+                ui.colored_label(ui.visuals().error_fg_color, message);
+            }
+        }
+        ui.horizontal(|ui| self.buttons(ui));
+    }
+
+    /// Random equations and saving the one shown.
+    fn buttons(&mut self, ui: &mut egui::Ui) {
+        if ui.button("🎲 Random").clicked() {
+            let equation = ChaosEquation::random(&mut rand::thread_rng());
+            self.set_equation(equation);
+            if self.custom {
+                self.set_custom_text(equation.expressions());
+            }
+            self.t = T_START;
+        }
+        if ui.button("☆ Save").clicked() {
+            self.favorites.push(Favorite {
+                code: self.code.clone(),
+                t: self.t,
+                custom: self.custom.then(|| self.custom_text.clone()),
+            });
+        }
+    }
+
+    /// Draw the orbits at the current `t` and a few before it, every point colored by how far
+    /// into its orbit it is.
+    fn plot(&self, ui: &mut egui::Ui) {
+        let size = egui::Vec2::splat(400.0);
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, egui::Color32::BLACK);
+
+        let scale = rect.width() / 2.0 / self.zoom;
+        let mut mesh = egui::Mesh::default();
+        for step in 0..TRAIL_STEPS {
+            let t = self.t - step as f32 * TRAIL_DT;
+            let fade = 1.0 - step as f32 / TRAIL_STEPS as f32;
+            for (i, point) in self.orbit(t).into_iter().enumerate() {
+                let pos = rect.center() + point.to_vec2() * egui::vec2(scale, -scale);
+                if !rect.contains(pos) {
+                    continue;
+                }
+                let hue = i as f32 / ITERATIONS as f32;
+                let color = egui::Color32::from(egui::epaint::Hsva::new(hue, 0.8, 1.0, fade));
+                mesh.add_colored_rect(
+                    egui::Rect::from_center_size(pos, egui::Vec2::splat(1.5)),
+                    color,
+                );
+            }
+        }
+        painter.add(mesh);
+    }
+}
+
+#[test]
+fn test_codes() {
+    let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(0);
+    for _ in 0..100 {
+        let equation = ChaosEquation::random(&mut rng);
+        assert_eq!(ChaosEquation::from_code(&equation.code()), Ok(equation));
+    }
+
+    // '_' is all -1, 'Z' all 1, and the first coefficient of a letter is its highest digit,
+    // as in CodeParade's codes.
+    assert_eq!(ChaosEquation::from_code("______").unwrap().params, [-1; 18]);
+    assert_eq!(ChaosEquation::from_code("zzzzzz").unwrap().params, [1; 18]);
+    let a = ChaosEquation::from_code("A_____").unwrap();
+    assert_eq!(a.params[..3], [-1, -1, 0]);
+    let c = ChaosEquation::from_code("__C___").unwrap();
+    assert_eq!(c.params[6..9], [-1, 0, -1]);
+
+    // Short codes are padded with '_'.
+    assert_eq!(
+        ChaosEquation::from_code("abc"),
+        ChaosEquation::from_code("ABC___")
+    );
+    assert!(ChaosEquation::from_code("").is_err());
+    assert!(ChaosEquation::from_code("ABCDEFG").is_err());
+    assert!(ChaosEquation::from_code("ABC12D").is_err());
+    assert!(ChaosEquation::from_code("ÄBC").is_err());
+}
+
+#[test]
+fn test_equations() {
+    let mut params = [0; 18];
+    // x' = x² - t, y' = -y + xt
+    params[0] = 1;
+    params[8] = -1;
+    params[9 + 7] = -1;
+    params[9 + 4] = 1;
+    let equation = ChaosEquation { params };
+    assert_eq!(equation.apply(2.0, 3.0, 0.5), (3.5, -2.0));
+    assert_eq!(equation.formulas(), ["x' = x² - t", "y' = xt - y"]);
+    assert_eq!(
+        ChaosEquation { params: [0; 18] }.formulas(),
+        ["x' = 0", "y' = 0"]
+    );
+
+    // Points that escape end the orbit, the rest stays finite.
+    let orbit = ChaosEquation { params: [1; 18] }.orbit(1.0);
+    assert!(orbit.len() < ITERATIONS);
+    assert!(orbit
+        .iter()
+        .all(|p| p.x.abs() < ESCAPE && p.y.abs() < ESCAPE));
+}
+
+#[test]
+fn test_custom_equations() {
+    // Every code equation typed in again as expressions iterates the same.
+    let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(1);
+    for _ in 0..20 {
+        let equation = ChaosEquation::random(&mut rng);
+        let custom = CustomEquation::parse(&equation.expressions()).unwrap();
+        assert_eq!(custom.orbit(-0.3), equation.orbit(-0.3));
+    }
+
+    let custom = CustomEquation::parse(&["x^2 - y*t + sin(t)".to_owned(), "x".to_owned()]);
+    assert_eq!(custom.unwrap().apply(2.0, 3.0, 0.0), (4.0, 2.0),);
+
+    let errors = CustomEquation::parse(&["x +".to_owned(), "y * z".to_owned()]).unwrap_err();
+    assert_eq!(errors[0].as_ref().map(|e| e.span.clone()), Some(3..3));
+    assert_eq!(errors[1].as_ref().map(|e| e.span.clone()), Some(4..5));
+    assert!(CustomEquation::parse(&["x".to_owned(), "y +".to_owned()]).unwrap_err()[0].is_none());
+}
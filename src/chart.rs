@@ -0,0 +1,487 @@
+//! A trading view of the [`crate::market`]: candlesticks, volume bars and moving averages, with
+//! panning, zooming and a crosshair reading out the candle under the pointer.
+
+use std::ops::Range;
+
+use eframe::egui;
+
+use crate::market::{self, Average, Candle};
+
+/// Trading days generated, about three years.
+const DAYS: usize = 750;
+
+/// Candles shown at first, and the fewest that can be zoomed in to.
+const DEFAULT_VISIBLE: f32 = 120.0;
+const MIN_VISIBLE: f32 = 10.0;
+
+/// The lines that can be drawn over the candles.
+const AVERAGES: [(Average, egui::Color32); 3] = [
+    (Average::Simple(20), egui::Color32::from_rgb(240, 190, 60)),
+    (Average::Simple(50), egui::Color32::from_rgb(80, 150, 240)),
+    (
+        Average::Exponential(12),
+        egui::Color32::from_rgb(200, 100, 220),
+    ),
+];
+
+const RISING: egui::Color32 = egui::Color32::from_rgb(38, 166, 154);
+const FALLING: egui::Color32 = egui::Color32::from_rgb(239, 83, 80);
+
+/// Room for the price labels on the right and the dates below.
+const PRICE_AXIS_WIDTH: f32 = 60.0;
+const DATE_AXIS_HEIGHT: f32 = 20.0;
+
+/// Part of the chart height the volume bars take, below the prices.
+const VOLUME_HEIGHT: f32 = 0.2;
+
+/// Date labels are at least this many points apart.
+const DATE_SPACING: f32 = 90.0;
+
+/// Which candles are on screen, by index. Fractional, so panning is smooth.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ChartView {
+    first: f32,
+    count: f32,
+}
+
+impl ChartView {
+    /// The last `count` of `len` candles.
+    fn latest(count: f32, len: usize) -> Self {
+        Self { first: 0.0, count }.clamped(len).pan(len as f32, len)
+    }
+
+    /// Keep at least [`MIN_VISIBLE`] candles on screen and none past either end.
+    fn clamped(self, len: usize) -> Self {
+        let len = len as f32;
+        let count = self.count.clamp(MIN_VISIBLE.min(len), len.max(1.0));
+        Self {
+            first: self.first.clamp(0.0, (len - count).max(0.0)),
+            count,
+        }
+    }
+
+    /// Move by `candles`, positive for later ones.
+    fn pan(self, candles: f32, len: usize) -> Self {
+        Self {
+            first: self.first + candles,
+            ..self
+        }
+        .clamped(len)
+    }
+
+    /// Show `factor` times as many candles, keeping the one at `anchor` in place.
+    fn zoom(self, factor: f32, anchor: f32, len: usize) -> Self {
+        let count = (self.count * factor).clamp(MIN_VISIBLE.min(len as f32), len as f32);
+        let first = anchor - (anchor - self.first) * count / self.count;
+        Self { first, count }.clamped(len)
+    }
+
+    /// Indices of the candles at least partly on screen.
+    fn visible(self, len: usize) -> Range<usize> {
+        let start = self.first.floor().max(0.0) as usize;
+        let end = ((self.first + self.count).ceil().max(0.0) as usize).min(len);
+        start.min(end)..end
+    }
+}
+
+/// A round step between grid lines about `approximate` apart: 1, 2 or 5 times a power of ten.
+fn nice_step(approximate: f32) -> f32 {
+    let magnitude = 10f32.powf(approximate.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|&step| step >= approximate)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// Shares as `1.2M` or `850K`.
+fn format_volume(volume: f32) -> String {
+    if volume >= 1e6 {
+        format!("{:.2}M", volume / 1e6)
+    } else {
+        format!("{:.0}K", volume / 1e3)
+    }
+}
+
+/// The window with the chart of a generated market.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ChartWindow {
+    pub open: bool,
+
+    /// Seed of the market shown.
+    seed: u64,
+
+    /// Which of the [`AVERAGES`] are drawn.
+    averages: [bool; 3],
+
+    show_volume: bool,
+
+    #[serde(skip)]
+    candles: Vec<Candle>,
+
+    /// Values of the [`AVERAGES`] at every candle.
+    #[serde(skip)]
+    average_values: Vec<Vec<Option<f32>>>,
+
+    /// `None` until the candles are there.
+    #[serde(skip)]
+    view: Option<ChartView>,
+
+    /// The market the candles are of.
+    #[serde(skip)]
+    generated_seed: Option<u64>,
+}
+
+impl Default for ChartWindow {
+    fn default() -> Self {
+        Self {
+            open: false,
+            seed: 7,
+            averages: [true, true, false],
+            show_volume: true,
+            candles: Vec::new(),
+            average_values: Vec::new(),
+            view: None,
+            generated_seed: None,
+        }
+    }
+}
+
+impl ChartWindow {
+    pub fn show(&mut self, ctx: &egui::Context) {
+        if self.generated_seed != Some(self.seed) {
+            self.candles = market::generate(self.seed, DAYS);
+            self.average_values = AVERAGES
+                .iter()
+                .map(|(average, _)| average.values(&self.candles))
+                .collect();
+            self.view = Some(ChartView::latest(DEFAULT_VISIBLE, self.candles.len()));
+            self.generated_seed = Some(self.seed);
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Market")
+            .open(&mut open)
+            .auto_sized()
+            .show(ctx, |ui| self.ui(ui));
+        self.open = open;
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if let (Some(first), Some(last)) = (self.candles.first(), self.candles.last()) {
+                let change = last.close / first.open - 1.0;
+                let color = if change >= 0.0 { RISING } else { FALLING };
+                ui.strong(format!("SYNTH {:.2}", last.close));
+                ui.colored_label(
+                    color,
+                    format!(
+                        "{:+.1} % since {}",
+                        change * 100.0,
+                        market::format_date(first.day)
+                    ),
+                );
+            }
+            ui.label("Seed").on_hover_text(
+                "The market is made up: a random walk with calm and wild stretches, the same for \
+                 the same seed",
+            );
+            ui.add(egui::DragValue::new(&mut self.seed));
+            if ui.button("🎲 New market").clicked() {
+                self.seed = rand::random::<u16>().into();
+            }
+        });
+        ui.horizontal(|ui| {
+            for ((average, color), shown) in AVERAGES.iter().zip(&mut self.averages) {
+                ui.checkbox(shown, egui::RichText::new(average.name()).color(*color));
+            }
+            ui.checkbox(&mut self.show_volume, "Volume");
+            if ui
+                .button("Reset view")
+                .on_hover_text("Drag to pan, scroll to zoom")
+                .clicked()
+            {
+                self.view = Some(ChartView::latest(DEFAULT_VISIBLE, self.candles.len()));
+            }
+        });
+        self.chart(ui);
+    }
+
+    fn chart(&mut self, ui: &mut egui::Ui) {
+        let len = self.candles.len();
+        let Some(mut view) = self.view else {
+            return;
+        };
+
+        let (rect, response) =
+            ui.allocate_exact_size(egui::vec2(720.0, 420.0), egui::Sense::drag());
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+        painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+
+        let plot = egui::Rect::from_min_max(
+            rect.min,
+            rect.max - egui::vec2(PRICE_AXIS_WIDTH, DATE_AXIS_HEIGHT),
+        );
+        let volume_height = if self.show_volume {
+            plot.height() * VOLUME_HEIGHT
+        } else {
+            0.0
+        };
+        let prices = egui::Rect::from_min_max(
+            plot.min + egui::vec2(0.0, 8.0),
+            plot.max - egui::vec2(0.0, volume_height + 8.0),
+        );
+        let volumes = egui::Rect::from_min_max(
+            egui::pos2(plot.left(), plot.bottom() - volume_height),
+            plot.max,
+        );
+
+        // Pan by dragging, zoom around the pointer by scrolling or pinching.
+        let candle_width = plot.width() / view.count;
+        if response.dragged() {
+            view = view.pan(-response.drag_delta().x / candle_width, len);
+        }
+        if let Some(pointer) = response.hover_pos() {
+            let (scroll, pinch) =
+                ui.input(|input| (input.smooth_scroll_delta.y, input.zoom_delta()));
+            let factor = (-scroll * 0.003).exp() / pinch;
+            if factor != 1.0 {
+                let anchor = view.first + (pointer.x - plot.left()) / candle_width;
+                view = view.zoom(factor, anchor, len);
+            }
+        }
+        self.view = Some(view);
+        let candle_width = plot.width() / view.count;
+        let x = |i: usize| plot.left() + (i as f32 + 0.5 - view.first) * candle_width;
+
+        let visible = view.visible(len);
+        let candles = &self.candles[visible.clone()];
+        if candles.is_empty() {
+            return;
+        }
+        let low = candles.iter().map(|c| c.low).fold(f32::INFINITY, f32::min);
+        let high = candles
+            .iter()
+            .map(|c| c.high)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let y = |price: f32| {
+            prices.bottom() - (price - low) / (high - low).max(f32::EPSILON) * prices.height()
+        };
+        let price_at = |y: f32| low + (prices.bottom() - y) / prices.height() * (high - low);
+
+        // Price grid and labels.
+        let grid_stroke = egui::Stroke::new(1.0, visuals.faint_bg_color.gamma_multiply(2.0));
+        let text_color = visuals.weak_text_color();
+        let font = egui::FontId::monospace(11.0);
+        let step = nice_step((high - low) / 6.0);
+        let mut price = (low / step).ceil() * step;
+        while price <= high {
+            let y = y(price);
+            painter.hline(plot.x_range(), y, grid_stroke);
+            painter.text(
+                egui::pos2(plot.right() + 4.0, y),
+                egui::Align2::LEFT_CENTER,
+                format!("{price:.2}"),
+                font.clone(),
+                text_color,
+            );
+            price += step;
+        }
+
+        // Dates, on every so many candles so they don't move while panning.
+        let every = (DATE_SPACING / candle_width).ceil().max(1.0) as usize;
+        for i in visible.clone().filter(|i| i % every == 0) {
+            painter.vline(x(i), plot.y_range(), grid_stroke);
+            painter.text(
+                egui::pos2(x(i), plot.bottom() + 3.0),
+                egui::Align2::CENTER_TOP,
+                market::format_date(self.candles[i].day),
+                font.clone(),
+                text_color,
+            );
+        }
+
+        // Volume bars under the candles.
+        let max_volume = candles.iter().map(|c| c.volume).fold(0.0, f32::max);
+        let body_width = (candle_width * 0.7).max(1.0);
+        if self.show_volume {
+            for (i, candle) in visible.clone().zip(candles) {
+                let height = candle.volume / max_volume * volumes.height();
+                let color = if candle.rising() { RISING } else { FALLING };
+                painter.rect_filled(
+                    egui::Rect::from_min_max(
+                        egui::pos2(x(i) - body_width / 2.0, volumes.bottom() - height),
+                        egui::pos2(x(i) + body_width / 2.0, volumes.bottom()),
+                    ),
+                    0.0,
+                    color.gamma_multiply(0.4),
+                );
+            }
+        }
+
+        // The candles: a wick from low to high and a body from open to close.
+        for (i, candle) in visible.clone().zip(candles) {
+            let color = if candle.rising() { RISING } else { FALLING };
+            painter.vline(
+                x(i),
+                y(candle.high)..=y(candle.low),
+                egui::Stroke::new(1.0, color),
+            );
+            let top = y(candle.open.max(candle.close));
+            let bottom = y(candle.open.min(candle.close)).max(top + 1.0);
+            painter.rect_filled(
+                egui::Rect::from_min_max(
+                    egui::pos2(x(i) - body_width / 2.0, top),
+                    egui::pos2(x(i) + body_width / 2.0, bottom),
+                ),
+                0.0,
+                color,
+            );
+        }
+
+        for (((_, color), values), shown) in
+            AVERAGES.iter().zip(&self.average_values).zip(self.averages)
+        {
+            if !shown {
+                continue;
+            }
+            let points: Vec<egui::Pos2> = visible
+                .clone()
+                .filter_map(|i| Some(egui::pos2(x(i), y(values[i]?))))
+                .collect();
+            painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, *color)));
+        }
+
+        // Crosshair on the candle under the pointer, with its numbers next to it.
+        let Some(pointer) = response.hover_pos().filter(|p| plot.contains(*p)) else {
+            return;
+        };
+        let i = (view.first + (pointer.x - plot.left()) / candle_width) as usize;
+        if !visible.contains(&i) {
+            return;
+        }
+        let crosshair = egui::Stroke::new(1.0, visuals.text_color().gamma_multiply(0.5));
+        painter.vline(x(i), plot.y_range(), crosshair);
+        painter.hline(plot.x_range(), pointer.y, crosshair);
+        if prices.y_range().contains(pointer.y) {
+            let label = painter.layout_no_wrap(
+                format!("{:.2}", price_at(pointer.y)),
+                font,
+                visuals.strong_text_color(),
+            );
+            let label_rect = egui::Align2::LEFT_CENTER.anchor_rect(egui::Rect::from_min_size(
+                egui::pos2(plot.right() + 4.0, pointer.y),
+                label.size(),
+            ));
+            painter.rect_filled(label_rect.expand(2.0), 2.0, visuals.widgets.active.bg_fill);
+            painter.galley(label_rect.min, label, visuals.strong_text_color());
+        }
+
+        let candle = self.candles[i];
+        let previous_close = i
+            .checked_sub(1)
+            .map_or(candle.open, |p| self.candles[p].close);
+        let averages: Vec<(String, f32, egui::Color32)> = AVERAGES
+            .iter()
+            .zip(&self.average_values)
+            .zip(self.averages)
+            .filter(|(_, shown)| *shown)
+            .filter_map(|(((average, color), values), _)| {
+                Some((average.name(), values[i]?, *color))
+            })
+            .collect();
+        response.on_hover_ui_at_pointer(|ui| {
+            ui.strong(market::format_date(candle.day));
+            egui::Grid::new("candle_tooltip")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    for (name, value) in [
+                        ("Open", candle.open),
+                        ("High", candle.high),
+                        ("Low", candle.low),
+                        ("Close", candle.close),
+                    ] {
+                        ui.label(name);
+                        ui.monospace(format!("{value:.2}"));
+                        ui.end_row();
+                    }
+                    let change = candle.close / previous_close - 1.0;
+                    ui.label("Change");
+                    let color = if change >= 0.0 { RISING } else { FALLING };
+                    ui.colored_label(color, format!("{:+.2} %", change * 100.0));
+                    ui.end_row();
+                    ui.label("Volume");
+                    ui.monospace(format_volume(candle.volume));
+                    ui.end_row();
+                    for (name, value, color) in averages {
+                        ui.colored_label(color, name);
+                        ui.monospace(format!("{value:.2}"));
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+}
+
+#[test]
+fn test_chart_view() {
+    let view = ChartView::latest(100.0, 750);
+    assert_eq!(
+        view,
+        ChartView {
+            first: 650.0,
+            count: 100.0
+        }
+    );
+    assert_eq!(view.visible(750), 650..750);
+
+    // Panning stops at either end.
+    assert_eq!(view.pan(10.0, 750), view);
+    assert_eq!(view.pan(-1000.0, 750).first, 0.0);
+    assert_eq!(view.pan(-10.5, 750).visible(750), 639..740);
+
+    // Zooming keeps the candle under the pointer where it was.
+    let zoomed = view.pan(-300.0, 750).zoom(0.5, 400.0, 750);
+    assert_eq!(
+        zoomed,
+        ChartView {
+            first: 375.0,
+            count: 50.0
+        }
+    );
+    assert_eq!(zoomed.zoom(0.01, 400.0, 750).count, MIN_VISIBLE);
+    assert_eq!(
+        zoomed.zoom(100.0, 400.0, 750),
+        ChartView {
+            first: 0.0,
+            count: 750.0
+        }
+    );
+
+    // Fewer candles than the view shows.
+    let short = ChartView::latest(100.0, 5);
+    assert_eq!(
+        (short, short.visible(5)),
+        (
+            ChartView {
+                first: 0.0,
+                count: 5.0
+            },
+            0..5
+        )
+    );
+    assert_eq!(ChartView::latest(100.0, 0).visible(0), 0..0);
+}
+
+#[test]
+fn test_axis_labels() {
+    assert_eq!(nice_step(0.7), 1.0);
+    assert_eq!(nice_step(1.3), 2.0);
+    assert_eq!(nice_step(3.0), 5.0);
+    assert_eq!(nice_step(6.0), 10.0);
+    assert_eq!(nice_step(23.0), 50.0);
+    assert_eq!(format_volume(1_234_567.0), "1.23M");
+    assert_eq!(format_volume(850_000.0), "850K");
+}
@@ -0,0 +1,523 @@
+//! Contact window: a small message form with pluggable ways of delivering the message.
+//!
+//! Where messages go is configured in the `[contact]` table of the content files: with an
+//! `endpoint` they are POSTed there as a form, otherwise a `mailto:` link to `email` is opened.
+//! Natively there is no TLS, so `https://` endpoints open the `mailto:` link too.
+
+use std::sync::mpsc::{channel, Receiver};
+
+use eframe::egui;
+
+/// The `[contact]` table of the content files.
+#[derive(Clone, Default, serde::Deserialize)]
+pub struct ContactConfig {
+    /// Address used by the `mailto:` fallback.
+    #[serde(default)]
+    pub email: String,
+
+    /// URL accepting a `application/x-www-form-urlencoded` POST with `name`, `email` and `message`.
+    pub endpoint: Option<String>,
+}
+
+impl ContactConfig {
+    fn backend(&self) -> Box<dyn SubmitBackend> {
+        match &self.endpoint {
+            Some(endpoint) if cfg!(target_arch = "wasm32") || endpoint.starts_with("http://") => {
+                Box::new(HttpBackend {
+                    endpoint: endpoint.clone(),
+                })
+            }
+            _ => Box::new(MailtoBackend {
+                to: self.email.clone(),
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ContactMessage {
+    pub name: String,
+    pub email: String,
+    pub message: String,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Email,
+    Message,
+}
+
+impl ContactMessage {
+    const MAX_NAME_LEN: usize = 100;
+    const MIN_MESSAGE_LEN: usize = 10;
+    const MAX_MESSAGE_LEN: usize = 5000;
+
+    /// Everything wrong with the message, empty if it can be sent.
+    pub fn validate(&self) -> Vec<(Field, &'static str)> {
+        let mut errors = Vec::new();
+
+        let name = self.name.trim();
+        if name.is_empty() {
+            errors.push((Field::Name, "Please enter your name."));
+        } else if name.chars().count() > Self::MAX_NAME_LEN {
+            errors.push((Field::Name, "That name is too long."));
+        }
+
+        if !is_valid_email(self.email.trim()) {
+            errors.push((Field::Email, "Please enter a valid email address."));
+        }
+
+        let message_len = self.message.trim().chars().count();
+        if message_len < Self::MIN_MESSAGE_LEN {
+            errors.push((Field::Message, "The message is too short."));
+        } else if message_len > Self::MAX_MESSAGE_LEN {
+            errors.push((Field::Message, "The message is too long."));
+        }
+        errors
+    }
+
+    /// The message as `application/x-www-form-urlencoded`.
+    fn form_body(&self) -> String {
+        format!(
+            "name={}&email={}&message={}",
+            percent_encode(self.name.trim()),
+            percent_encode(self.email.trim()),
+            percent_encode(self.message.trim()),
+        )
+    }
+}
+
+/// Loose check for `local@domain.tld`, the real check is whether the reply arrives.
+fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !email.contains(char::is_whitespace)
+        && !domain.contains('@')
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+}
+
+/// Escape everything but RFC 3986 unreserved characters.
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char);
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Outcome of a submission: a message for the visitor on success, or what went wrong.
+pub type SubmitResult = Result<String, String>;
+
+/// A way of delivering a [`ContactMessage`] to the portfolio owner.
+pub trait SubmitBackend {
+    /// Start sending `message`, the outcome arrives on the returned channel.
+    fn submit(&self, ctx: &egui::Context, message: &ContactMessage) -> Receiver<SubmitResult>;
+}
+
+/// Opens the visitor's mail client with the message filled in.
+pub struct MailtoBackend {
+    pub to: String,
+}
+
+impl MailtoBackend {
+    fn url(&self, message: &ContactMessage) -> String {
+        format!(
+            "mailto:{}?subject={}&body={}",
+            self.to,
+            percent_encode(&format!("Portfolio contact from {}", message.name.trim())),
+            percent_encode(&format!(
+                "{}\n\n{} <{}>",
+                message.message.trim(),
+                message.name.trim(),
+                message.email.trim()
+            )),
+        )
+    }
+}
+
+impl SubmitBackend for MailtoBackend {
+    fn submit(&self, ctx: &egui::Context, message: &ContactMessage) -> Receiver<SubmitResult> {
+        let (sender, receiver) = channel();
+        let result = if self.to.is_empty() {
+            Err("No contact address is configured.".to_owned())
+        } else {
+            ctx.open_url(egui::OpenUrl::new_tab(self.url(message)));
+            Ok("Your mail client should have opened with the message.".to_owned())
+        };
+        sender.send(result).ok();
+        receiver
+    }
+}
+
+/// POSTs the message as a form to an HTTP endpoint.
+///
+/// Natively only plain `http://` endpoints are supported, e.g. for testing against a local
+/// server, the browser handles `https://`.
+pub struct HttpBackend {
+    pub endpoint: String,
+}
+
+impl SubmitBackend for HttpBackend {
+    fn submit(&self, ctx: &egui::Context, message: &ContactMessage) -> Receiver<SubmitResult> {
+        let (sender, receiver) = channel();
+        let endpoint = self.endpoint.clone();
+        let body = message.form_body();
+        let ctx = ctx.clone();
+        let done = move |result: Result<(), String>| {
+            sender
+                .send(result.map(|()| "Thanks, your message was sent!".to_owned()))
+                .ok();
+            ctx.request_repaint();
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::spawn(move || done(post_form(&endpoint, &body)));
+
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(async move {
+            done(post_form(&endpoint, &body).await.map_err(|err| {
+                use wasm_bindgen::JsCast as _;
+                // Our own errors are strings, the browser's are `Error` objects.
+                err.as_string()
+                    .or_else(|| err.dyn_ref::<js_sys::Error>().map(|e| e.message().into()))
+                    .unwrap_or_else(|| format!("{err:?}"))
+            }));
+        });
+
+        receiver
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn post_form(endpoint: &str, body: &str) -> Result<(), String> {
+    use std::io::{BufRead as _, BufReader, Write as _};
+
+    let rest = endpoint
+        .strip_prefix("http://")
+        .ok_or("Only http:// contact endpoints are supported natively.")?;
+    let (host, path) = match rest.find('/') {
+        Some(slash) => (&rest[..slash], &rest[slash..]),
+        None => (rest, "/"),
+    };
+    let address = if host.contains(':') {
+        host.to_owned()
+    } else {
+        format!("{host}:80")
+    };
+
+    let io_err = |err: std::io::Error| format!("Could not reach {host}: {err}");
+    let mut stream = std::net::TcpStream::connect(address).map_err(io_err)?;
+    let timeout = Some(std::time::Duration::from_secs(10));
+    stream.set_read_timeout(timeout).map_err(io_err)?;
+    stream.set_write_timeout(timeout).map_err(io_err)?;
+
+    write!(
+        stream,
+        "POST {path} HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         Content-Type: application/x-www-form-urlencoded\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {body}",
+        body.len()
+    )
+    .map_err(io_err)?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut status_line)
+        .map_err(io_err)?;
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+    if status.starts_with('2') {
+        Ok(())
+    } else {
+        Err(format!("The server answered: {}", status_line.trim()))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn post_form(endpoint: &str, body: &str) -> Result<(), wasm_bindgen::JsValue> {
+    use wasm_bindgen::JsCast as _;
+    use wasm_bindgen_futures::JsFuture;
+
+    let mut init = web_sys::RequestInit::new();
+    init.method("POST");
+    init.body(Some(&body.into()));
+    let request = web_sys::Request::new_with_str_and_init(endpoint, &init)?;
+    request
+        .headers()
+        .set("Content-Type", "application/x-www-form-urlencoded")?;
+
+    let window = web_sys::window().ok_or("no window")?;
+    let response: web_sys::Response = JsFuture::from(window.fetch_with_request(&request))
+        .await?
+        .dyn_into()?;
+    if response.ok() {
+        Ok(())
+    } else {
+        Err(format!(
+            "The server answered: {} {}",
+            response.status(),
+            response.status_text()
+        )
+        .into())
+    }
+}
+
+#[derive(Default)]
+enum SubmitState {
+    #[default]
+    Editing,
+    Sending(Receiver<SubmitResult>),
+    Sent(String),
+    Failed(String),
+}
+
+#[derive(Default)]
+pub struct ContactWindow {
+    pub open: bool,
+    message: ContactMessage,
+    state: SubmitState,
+
+    /// Only complain about the fields once the visitor tried to send them.
+    show_errors: bool,
+}
+
+impl ContactWindow {
+    pub fn show(&mut self, ctx: &egui::Context, config: &ContactConfig) {
+        if let SubmitState::Sending(receiver) = &self.state {
+            match receiver.try_recv() {
+                Ok(Ok(thanks)) => {
+                    self.message = ContactMessage::default();
+                    self.show_errors = false;
+                    self.state = SubmitState::Sent(thanks);
+                }
+                Ok(Err(err)) => self.state = SubmitState::Failed(err),
+                Err(std::sync::mpsc::TryRecvError::Empty) => {}
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    self.state = SubmitState::Failed("The message got lost.".to_owned());
+                }
+            }
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Contact")
+            .open(&mut open)
+            .auto_sized()
+            .show(ctx, |ui| self.ui(ui, config));
+        self.open = open;
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, config: &ContactConfig) {
+        let errors = self.message.validate();
+        let sending = matches!(self.state, SubmitState::Sending(_));
+        let error_color = ui.visuals().error_fg_color;
+        let field_error = |ui: &mut egui::Ui, field: Field| {
+            if let Some((_, error)) = errors.iter().find(|(f, _)| *f == field) {
+                ui.colored_label(error_color, *error);
+            }
+        };
+
+        ui.add_enabled_ui(!sending, |ui| {
+            egui::Grid::new("contact_form")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Name");
+                    ui.text_edit_singleline(&mut self.message.name);
+                    ui.end_row();
+                    if self.show_errors {
+                        ui.label("");
+                        field_error(ui, Field::Name);
+                        ui.end_row();
+                    }
+
+                    ui.label("Email");
+                    ui.text_edit_singleline(&mut self.message.email);
+                    ui.end_row();
+                    if self.show_errors {
+                        ui.label("");
+                        field_error(ui, Field::Email);
+                        ui.end_row();
+                    }
+
+                    ui.label("Message");
+                    ui.add(egui::TextEdit::multiline(&mut self.message.message).desired_rows(6));
+                    ui.end_row();
+                    if self.show_errors {
+                        ui.label("");
+                        field_error(ui, Field::Message);
+                        ui.end_row();
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!sending, egui::Button::new("Send"))
+                .clicked()
+            {
+                self.show_errors = true;
+                if errors.is_empty() {
+                    let receiver = config.backend().submit(ui.ctx(), &self.message);
+                    self.state = SubmitState::Sending(receiver);
+                }
+            }
+            match &self.state {
+                SubmitState::Editing => {}
+                SubmitState::Sending(_) => {
+                    ui.spinner();
+                    ui.label("Sending…");
+                }
+                SubmitState::Sent(thanks) => {
+                    ui.label(thanks);
+                }
+                SubmitState::Failed(err) => {
+                    ui.colored_label(error_color, format!("Sending failed: {err}"));
+                }
+            }
+        });
+    }
+}
+
+#[test]
+fn test_contact_validation() {
+    let mut message = ContactMessage {
+        name: "Ada".to_owned(),
+        email: "ada@example.com".to_owned(),
+        message: "Hello there, nice portfolio!".to_owned(),
+    };
+    assert!(message.validate().is_empty());
+
+    message.name = "  ".to_owned();
+    message.email = "ada@example".to_owned();
+    message.message = "hi".to_owned();
+    let fields: Vec<_> = message.validate().into_iter().map(|(f, _)| f).collect();
+    assert_eq!(fields, vec![Field::Name, Field::Email, Field::Message]);
+
+    for invalid in [
+        "",
+        "ada",
+        "@example.com",
+        "ada@.com",
+        "a da@example.com",
+        "a@b@c.com",
+    ] {
+        assert!(!is_valid_email(invalid), "{invalid}");
+    }
+
+    let mailto = MailtoBackend {
+        to: "me@example.com".to_owned(),
+    };
+    let url = mailto.url(&ContactMessage {
+        name: "Ada L".to_owned(),
+        email: "ada@example.com".to_owned(),
+        message: "a&b=c".to_owned(),
+    });
+    assert_eq!(
+        url,
+        "mailto:me@example.com?subject=Portfolio%20contact%20from%20Ada%20L\
+         &body=a%26b%3Dc%0A%0AAda%20L%20%3Cada%40example.com%3E"
+    );
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn test_http_backend_against_mock_server() {
+    use std::io::{Read as _, Write as _};
+
+    /// Answer a single request with `status`, handing back what was received.
+    fn mock_server(status: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/contact", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            // Read until the whole body announced by Content-Length is in.
+            loop {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length: usize = head
+                        .lines()
+                        .find_map(|l| l.strip_prefix("Content-Length: "))
+                        .and_then(|l| l.parse().ok())
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+            write!(stream, "HTTP/1.1 {status}\r\nContent-Length: 0\r\n\r\n").unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (endpoint, handle)
+    }
+
+    let ctx = egui::Context::default();
+    let message = ContactMessage {
+        name: "Ada".to_owned(),
+        email: "ada@example.com".to_owned(),
+        message: "Hello there!".to_owned(),
+    };
+    let timeout = std::time::Duration::from_secs(10);
+
+    let (endpoint, server) = mock_server("200 OK");
+    let result = HttpBackend { endpoint }
+        .submit(&ctx, &message)
+        .recv_timeout(timeout)
+        .unwrap();
+    assert!(result.is_ok(), "{result:?}");
+    let request = server.join().unwrap();
+    assert!(
+        request.starts_with("POST /contact HTTP/1.1\r\n"),
+        "{request}"
+    );
+    assert!(request.ends_with("name=Ada&email=ada%40example.com&message=Hello%20there%21"));
+
+    let (endpoint, server) = mock_server("500 Internal Server Error");
+    let result = HttpBackend { endpoint }
+        .submit(&ctx, &message)
+        .recv_timeout(timeout)
+        .unwrap();
+    assert_eq!(
+        result,
+        Err("The server answered: HTTP/1.1 500 Internal Server Error".to_owned())
+    );
+    server.join().unwrap();
+
+    let result = HttpBackend {
+        endpoint: "https://example.com".to_owned(),
+    }
+    .submit(&ctx, &message)
+    .recv_timeout(timeout)
+    .unwrap();
+    assert!(result.is_err());
+
+    // Configured with https, the message goes through the mail client instead.
+    let config = ContactConfig {
+        email: String::new(),
+        endpoint: Some("https://example.com/contact".to_owned()),
+    };
+    let result = config
+        .backend()
+        .submit(&ctx, &message)
+        .recv_timeout(timeout)
+        .unwrap();
+    assert_eq!(result, Err("No contact address is configured.".to_owned()));
+}
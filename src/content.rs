@@ -0,0 +1,279 @@
+//! Large text contents of the portfolio, loaded from `assets/text_contents*.toml`.
+//!
+//! `text_contents.toml` holds the default locale. Every other locale lives in
+//! `text_contents.<code>.toml` and only needs the keys it actually translates,
+//! anything missing falls back to the default locale.
+//!
+//! Where the files are read from is up to a [`ContentSource`], see [`crate::content_source`].
+
+pub use crate::content_source::ContentSource;
+
+use crate::contact::ContactConfig;
+use crate::content_schema;
+
+/// Locale used when no other locale matches, and the one every other locale falls back to.
+pub const DEFAULT_LOCALE: &str = "en";
+
+pub struct Locale {
+    /// Primary language subtag, e.g. `en` or `es`.
+    pub code: &'static str,
+
+    /// Name shown in the language selector, in its own language.
+    pub name: &'static str,
+
+    /// Copy of the content file compiled into the binary.
+    pub embedded: &'static str,
+}
+
+impl Locale {
+    /// Name of the content file in `assets/`.
+    pub fn file_name(&self) -> String {
+        if self.code == DEFAULT_LOCALE {
+            "text_contents.toml".to_owned()
+        } else {
+            format!("text_contents.{}.toml", self.code)
+        }
+    }
+}
+
+/// Every locale compiled into the app, the first entry is [`DEFAULT_LOCALE`].
+pub const LOCALES: &[Locale] = &[
+    Locale {
+        code: DEFAULT_LOCALE,
+        name: "English",
+        embedded: include_str!("../assets/text_contents.toml"),
+    },
+    Locale {
+        code: "es",
+        name: "Español",
+        embedded: include_str!("../assets/text_contents.es.toml"),
+    },
+];
+
+// Define structs that match the TOML structure
+#[derive(Default, serde::Deserialize)]
+pub struct TextContents {
+    pub work_experience: WorkExperience,
+    pub biography: TextBlock,
+    pub about_page: TextBlock,
+
+    #[serde(default)]
+    pub contact: ContactConfig,
+}
+
+#[derive(Default, serde::Deserialize)]
+pub struct TextBlock {
+    pub text: String,
+}
+
+#[derive(Default, serde::Deserialize)]
+pub struct WorkExperience {
+    pub lucid_software: ExperienceDetails,
+    pub freelance_projects: ExperienceDetails,
+}
+
+#[derive(Default, serde::Deserialize)]
+pub struct ExperienceDetails {
+    pub description: String,
+}
+
+/// A content file that could not be turned into [`TextContents`].
+#[derive(Debug)]
+pub struct ContentError {
+    /// Name of the offending file, e.g. `text_contents.es.toml`.
+    pub file: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ContentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.file, self.message)
+    }
+}
+
+impl TextContents {
+    /// Load the contents for `locale` from `source`, unknown locales get the default locale.
+    ///
+    /// Files the source does not have are taken from the copy embedded in the binary.
+    pub fn load(source: &mut dyn ContentSource, locale: &str) -> Result<Self, ContentError> {
+        let mut read = |locale: &Locale| {
+            let file = locale.file_name();
+            let text = source
+                .read(&file)
+                .unwrap_or_else(|| locale.embedded.to_owned());
+            (file, text)
+        };
+
+        let default = read(&LOCALES[0]);
+        let localized = find_locale(locale)
+            .filter(|l| l.code != DEFAULT_LOCALE)
+            .map(read);
+        Self::parse_with_fallback(
+            (&default.0, &default.1),
+            localized
+                .as_ref()
+                .map(|(file, text)| (file.as_str(), text.as_str())),
+        )
+    }
+
+    /// Every `EasyMark` text in the contents as `(title, TOML key path, text)`.
+    pub fn texts_mut(&mut self) -> [(&'static str, &'static [&'static str], &mut String); 4] {
+        [
+            ("About", TEXT_KEYS[0], &mut self.about_page.text),
+            ("Bio", TEXT_KEYS[1], &mut self.biography.text),
+            (
+                "Lucid Software",
+                TEXT_KEYS[2],
+                &mut self.work_experience.lucid_software.description,
+            ),
+            (
+                "Freelance Projects",
+                TEXT_KEYS[3],
+                &mut self.work_experience.freelance_projects.description,
+            ),
+        ]
+    }
+
+    /// Parse `localized` on top of `default`, so keys missing from `localized` keep their default value.
+    ///
+    /// Both are `(file name, TOML)` pairs, the file name is only used for error messages.
+    /// Files of an older schema version are migrated first.
+    pub fn parse_with_fallback(
+        default: (&str, &str),
+        localized: Option<(&str, &str)>,
+    ) -> Result<Self, ContentError> {
+        let parse = |(file, text): (&str, &str)| {
+            let error = |message| ContentError {
+                file: file.to_owned(),
+                message,
+            };
+            let mut table =
+                toml::from_str::<toml::Table>(text).map_err(|e| error(e.to_string()))?;
+            content_schema::migrate(&mut table).map_err(error)?;
+            Ok(table)
+        };
+
+        let mut table = parse(default)?;
+        let mut localized_keys = Vec::new();
+        if let Some(localized) = localized {
+            let localized_table = parse(localized)?;
+            localized_keys.extend(
+                TEXT_KEYS
+                    .iter()
+                    .filter(|path| content_schema::lookup(&localized_table, path).is_some()),
+            );
+            merge_tables(&mut table, localized_table);
+        }
+
+        for path in TEXT_KEYS {
+            // Blame the file the value came from.
+            let file = match localized {
+                Some((file, _)) if localized_keys.contains(&path) => file,
+                _ => default.0,
+            };
+            let key = path.join(".");
+            match content_schema::lookup(&table, path) {
+                Some(toml::Value::String(_)) => {}
+                Some(other) => {
+                    return Err(ContentError {
+                        file: file.to_owned(),
+                        message: format!("`{key}` must be a string, found {}", other.type_str()),
+                    })
+                }
+                None => {
+                    return Err(ContentError {
+                        file: file.to_owned(),
+                        message: format!("missing key `{key}`"),
+                    })
+                }
+            }
+        }
+
+        toml::Value::Table(table)
+            .try_into()
+            .map_err(|err: toml::de::Error| ContentError {
+                file: default.0.to_owned(),
+                message: err.to_string(),
+            })
+    }
+}
+
+/// TOML key path of every text, in the order of [`TextContents::texts_mut`].
+const TEXT_KEYS: [&[&str]; 4] = [
+    &["about_page", "text"],
+    &["biography", "text"],
+    &["work_experience", "lucid_software", "description"],
+    &["work_experience", "freelance_projects", "description"],
+];
+
+/// Find a compiled in locale by its code, ignoring case and any region subtag (`es-MX` finds `es`).
+pub fn find_locale(code: &str) -> Option<&'static Locale> {
+    let primary = code.split(['-', '_']).next().unwrap_or(code);
+    LOCALES
+        .iter()
+        .find(|l| l.code.eq_ignore_ascii_case(primary))
+}
+
+/// The preferred locale of the browser, if we have content for it.
+#[cfg(target_arch = "wasm32")]
+pub fn browser_locale() -> Option<&'static str> {
+    let language = web_sys::window()?.navigator().language()?;
+    find_locale(&language).map(|l| l.code)
+}
+
+/// Recursively copy every key of `overlay` into `base`, replacing leaf values.
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_tables(base, overlay);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_locale_fallback() {
+    let default = "[about_page]\ntext = 'about'\n[biography]\ntext = 'bio'\n\
+                   [work_experience.lucid_software]\ndescription = 'lucid'\n\
+                   [work_experience.freelance_projects]\ndescription = 'freelance'\n";
+    let localized = "[biography]\ntext = 'biografía'\n\
+                     [work_experience.lucid_software]\ndescription = 'lúcido'\n";
+
+    let contents =
+        TextContents::parse_with_fallback(("en", default), Some(("es", localized))).unwrap();
+    assert_eq!(contents.about_page.text, "about");
+    assert_eq!(contents.biography.text, "biografía");
+    assert_eq!(
+        contents.work_experience.lucid_software.description,
+        "lúcido"
+    );
+    assert_eq!(
+        contents.work_experience.freelance_projects.description,
+        "freelance"
+    );
+
+    let err = TextContents::parse_with_fallback(("en", default), Some(("es", "[biography"))).err();
+    assert_eq!(err.map(|e| e.file), Some("es".to_owned()));
+
+    let err = TextContents::parse_with_fallback(("en", "[biography]\ntext = 'bio'"), None).err();
+    assert_eq!(
+        err.map(|e| e.message),
+        Some("missing key `about_page.text`".to_owned())
+    );
+    let err =
+        TextContents::parse_with_fallback(("en", default), Some(("es", "[biography]\ntext = 1")))
+            .err()
+            .unwrap();
+    assert_eq!(err.file, "es");
+    assert!(err.message.contains("`biography.text`"), "{err}");
+
+    assert_eq!(find_locale("es-MX").map(|l| l.code), Some("es"));
+    assert!(find_locale("fr").is_none());
+    for locale in LOCALES {
+        TextContents::load(&mut crate::content_source::EmbeddedSource, locale.code).unwrap();
+    }
+}
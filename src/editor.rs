@@ -0,0 +1,285 @@
+//! In-app editor for the `EasyMark` text contents, with a live preview.
+//!
+//! Natively the edited file is saved back into the content directory (and picked up again by
+//! the hot reload), on the web it is downloaded so it can be copied into `assets/`.
+
+use eframe::egui;
+
+use crate::content::{self, ContentSource, TextContents};
+use crate::content_schema;
+use crate::easy_mark_parser::Parser;
+use crate::easy_mark_viewer::easy_mark;
+
+/// Whether the editor can be opened: always natively, on the web only with `#edit` in the URL.
+pub fn available() -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    return true;
+
+    #[cfg(target_arch = "wasm32")]
+    return web_sys::window()
+        .and_then(|w| w.location().hash().ok())
+        .map_or(false, |hash| hash == "#edit");
+}
+
+#[derive(Default)]
+pub struct ContentEditor {
+    pub open: bool,
+
+    /// Index into [`TextContents::texts_mut`].
+    selected: usize,
+
+    /// Key paths of the texts edited since the last save.
+    dirty: Vec<&'static [&'static str]>,
+
+    /// Locale the [`Self::dirty`] texts were edited in.
+    dirty_locale: String,
+
+    /// Result of the last save, shown under the editor.
+    status: Option<Result<String, String>>,
+}
+
+impl ContentEditor {
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        contents: &mut TextContents,
+        source: &mut dyn ContentSource,
+        locale: &str,
+    ) {
+        let mut open = self.open;
+        egui::Window::new("Editor")
+            .open(&mut open)
+            .default_size([900.0, 500.0])
+            .show(ctx, |ui| self.ui(ui, contents, source, locale));
+        self.open = open;
+    }
+
+    fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        contents: &mut TextContents,
+        source: &mut dyn ContentSource,
+        locale: &str,
+    ) {
+        if self.dirty_locale != locale {
+            self.discard_edits("the language changed");
+        }
+        let mut texts = contents.texts_mut();
+
+        ui.horizontal(|ui| {
+            for (i, (title, _, _)) in texts.iter().enumerate() {
+                ui.selectable_value(&mut self.selected, i, *title);
+            }
+        });
+        ui.separator();
+
+        let (_, path, text) = &mut texts[self.selected];
+        ui.columns(2, |columns| {
+            egui::ScrollArea::vertical()
+                .id_source("editor_source")
+                .show(&mut columns[0], |ui| {
+                    let response = ui.add(
+                        egui::TextEdit::multiline(*text)
+                            .code_editor()
+                            .desired_width(f32::INFINITY)
+                            .desired_rows(20),
+                    );
+                    if response.changed() && !self.dirty.contains(path) {
+                        self.dirty.push(path);
+                        self.dirty_locale = locale.to_owned();
+                    }
+                });
+            egui::ScrollArea::vertical()
+                .id_source("editor_preview")
+                .show(&mut columns[1], |ui| easy_mark(ui, text));
+        });
+        ui.separator();
+
+        let problems = diagnostics(text);
+        if problems.is_empty() {
+            ui.weak("No problems found.");
+        }
+        for problem in &problems {
+            ui.colored_label(ui.visuals().warn_fg_color, problem.to_string());
+        }
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            let label = if cfg!(target_arch = "wasm32") {
+                "Download TOML"
+            } else {
+                "Save TOML"
+            };
+            if ui
+                .add_enabled(!self.dirty.is_empty(), egui::Button::new(label))
+                .clicked()
+            {
+                let result = self.save(contents, source, locale);
+                if result.is_ok() {
+                    self.dirty.clear();
+                }
+                self.status = Some(result);
+            }
+            match &self.status {
+                Some(Ok(file)) => {
+                    ui.label(format!("Saved {file}"));
+                }
+                Some(Err(err)) => {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+                None => {}
+            }
+        });
+    }
+
+    /// Forget the unsaved edits because the texts they were made in were replaced, e.g. by a
+    /// reload, saying so under the editor.
+    pub fn discard_edits(&mut self, reason: &str) {
+        if !self.dirty.is_empty() {
+            self.dirty.clear();
+            self.status = Some(Err(format!("Unsaved edits were discarded, {reason}")));
+        }
+    }
+
+    /// Write the edited texts into the content file of `locale`, returning its name.
+    ///
+    /// Only the edited keys are replaced so translations keep falling back for the rest.
+    /// Older files are upgraded to the current schema, comments in the file are not preserved.
+    fn save(
+        &self,
+        contents: &mut TextContents,
+        source: &mut dyn ContentSource,
+        locale: &str,
+    ) -> Result<String, String> {
+        let locale = content::find_locale(locale).unwrap_or(&content::LOCALES[0]);
+        let file = locale.file_name();
+        let current = source
+            .read(&file)
+            .unwrap_or_else(|| locale.embedded.to_owned());
+        let mut table: toml::Table =
+            toml::from_str(&current).map_err(|err| format!("{file}: {err}"))?;
+        content_schema::migrate(&mut table).map_err(|err| format!("{file}: {err}"))?;
+
+        for (_, path, text) in contents.texts_mut() {
+            if self.dirty.contains(&path) {
+                set_key(&mut table, path, text.clone());
+            }
+        }
+
+        let toml = toml::to_string_pretty(&table).map_err(|err| err.to_string())?;
+        source.save(&file, &toml)?;
+        Ok(file)
+    }
+}
+
+/// Set the string at `path`, creating any missing tables on the way.
+fn set_key(table: &mut toml::Table, path: &[&str], value: String) {
+    let (key, tables) = path.split_last().expect("empty key path");
+    let mut table = table;
+    for name in tables {
+        let entry = table
+            .entry(*name)
+            .or_insert_with(|| toml::Value::Table(Default::default()));
+        if !entry.is_table() {
+            *entry = toml::Value::Table(Default::default());
+        }
+        table = entry.as_table_mut().expect("just made sure it is a table");
+    }
+    table.insert((*key).to_owned(), toml::Value::String(value));
+}
+
+/// Something in an `EasyMark` text that probably doesn't render the way it was meant to.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    /// 1-based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Find style markers left open at the end of a line and unterminated code blocks.
+pub fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    let mut problems = Vec::new();
+    let mut code_block_start = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        if line.starts_with("```") {
+            code_block_start = match code_block_start {
+                Some(_) => None,
+                None => Some(line_number),
+            };
+            continue;
+        }
+        if code_block_start.is_some() {
+            continue;
+        }
+
+        // Styles are reset at the end of every line, so a line on its own parses the same.
+        let mut parser = Parser::new(line);
+        parser.by_ref().for_each(drop);
+        let style = parser.style();
+        for (open, marker, name) in [
+            (style.strong, '*', "bold"),
+            (style.underline, '_', "underline"),
+            (style.strikethrough, '~', "strikethrough"),
+            (
+                style.italics,
+                '/',
+                "italics (escape it as `\\/` for a slash)",
+            ),
+            (style.small, '$', "small"),
+            (style.raised, '^', "raised"),
+        ] {
+            if open {
+                problems.push(Diagnostic {
+                    line: line_number,
+                    message: format!("unclosed `{marker}`: {name}"),
+                });
+            }
+        }
+    }
+
+    if let Some(line) = code_block_start {
+        problems.push(Diagnostic {
+            line,
+            message: "code block is never closed with ```".to_owned(),
+        });
+    }
+    problems
+}
+
+#[test]
+fn test_diagnostics() {
+    assert!(diagnostics("*bold* and _underline_\nC\\/C++").is_empty());
+
+    let problems = diagnostics("fine\nC/C++ is *great\n```\n/code/ *\n");
+    assert_eq!(
+        problems.iter().map(|p| p.line).collect::<Vec<_>>(),
+        vec![2, 2, 3]
+    );
+    assert!(problems[0].message.contains('*'));
+    assert!(problems[1].message.contains('/'));
+
+    let mut editor = ContentEditor {
+        dirty: vec![&["biography", "text"]],
+        dirty_locale: "es".to_owned(),
+        ..Default::default()
+    };
+    editor.discard_edits("the contents were reloaded");
+    assert!(editor.dirty.is_empty());
+    assert!(matches!(&editor.status, Some(Err(err)) if err.contains("reloaded")));
+    // Nothing to discard, nothing to say.
+    editor.status = None;
+    editor.discard_edits("the contents were reloaded");
+    assert!(editor.status.is_none());
+
+    let mut table = toml::Table::new();
+    set_key(&mut table, &["a", "b", "c"], "text".to_owned());
+    assert_eq!(table["a"]["b"]["c"].as_str(), Some("text"));
+}
@@ -0,0 +1,754 @@
+//! Math typed in by the user, like `x^2 - y*t + sin(t)`, turned into something that can be
+//! evaluated many times a frame.
+//!
+//! The text is split into tokens and parsed with a Pratt parser. Constant parts are folded
+//! while parsing, and the tree is compiled to a small stack bytecode over named variables.
+//! Every error carries the [`Span`] of the text it is about, to highlight it.
+
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::CharIndices;
+
+use eframe::egui;
+
+/// Bytes of the text something came from.
+pub type Span = Range<usize>;
+
+/// How deeply an expression may nest, and so how many values its evaluation may stack up.
+/// Keeps a text like `((((((…` from overflowing the stack of the parser.
+const MAX_DEPTH: usize = 64;
+
+/// Constants that can be used by name, unless there is a variable with the same name.
+const CONSTANTS: [(&str, f32); 3] = [
+    ("pi", std::f32::consts::PI),
+    ("tau", std::f32::consts::TAU),
+    ("e", std::f32::consts::E),
+];
+
+/// Text that isn't a valid expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    /// The offending part of the text, empty at the end of it.
+    pub span: Span,
+}
+
+impl ParseError {
+    fn new(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// 1-based column of the start of the span, in characters.
+    pub fn column(&self, text: &str) -> usize {
+        text.get(..self.span.start)
+            .map_or(0, |before| before.chars().count())
+            + 1
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TokenKind {
+    Number(f32),
+    Name,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    Open,
+    Close,
+    Comma,
+    End,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    span: Span,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    // Consume characters while `accept` holds, returning where they end.
+    let take_while = |chars: &mut Peekable<CharIndices<'_>>, accept: fn(char) -> bool| {
+        while chars.next_if(|&(_, c)| accept(c)).is_some() {}
+        chars.peek().map_or(text.len(), |&(i, _)| i)
+    };
+    while let Some((start, c)) = chars.next() {
+        let span = start..start + c.len_utf8();
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '0'..='9' | '.' => {
+                let mut end = take_while(&mut chars, |c| c.is_ascii_digit() || c == '.');
+                // An exponent, only if digits follow, `2e` is a number and a name.
+                let exponent = text[end..]
+                    .strip_prefix(['e', 'E'])
+                    .map(|rest| rest.strip_prefix(['+', '-']).unwrap_or(rest));
+                if exponent.map_or(false, |digits| {
+                    digits.starts_with(|c: char| c.is_ascii_digit())
+                }) {
+                    chars.next();
+                    chars.next_if(|&(_, c)| c == '+' || c == '-');
+                    end = take_while(&mut chars, |c| c.is_ascii_digit());
+                }
+                let number = text[start..end].parse().map_err(|_| {
+                    ParseError::new(
+                        format!("`{}` isn't a number", &text[start..end]),
+                        start..end,
+                    )
+                })?;
+                tokens.push(Token {
+                    kind: TokenKind::Number(number),
+                    span: start..end,
+                });
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let end = take_while(&mut chars, |c| {
+                    c.is_alphabetic() || c.is_ascii_digit() || c == '_'
+                });
+                tokens.push(Token {
+                    kind: TokenKind::Name,
+                    span: start..end,
+                });
+                continue;
+            }
+            // Squares and cubes as they are written in the formulas of the chaos equations.
+            '²' | '³' => {
+                let power = if c == '²' { 2.0 } else { 3.0 };
+                tokens.push(Token {
+                    kind: TokenKind::Caret,
+                    span: span.clone(),
+                });
+                TokenKind::Number(power)
+            }
+            '+' => TokenKind::Plus,
+            '-' | '−' => TokenKind::Minus,
+            '*' | '×' | '·' => TokenKind::Star,
+            '/' | '÷' => TokenKind::Slash,
+            '%' => TokenKind::Percent,
+            '^' => TokenKind::Caret,
+            '(' => TokenKind::Open,
+            ')' => TokenKind::Close,
+            ',' => TokenKind::Comma,
+            c => return Err(ParseError::new(format!("unexpected `{c}`"), span)),
+        };
+        tokens.push(Token { kind, span });
+    }
+    tokens.push(Token {
+        kind: TokenKind::End,
+        span: text.len()..text.len(),
+    });
+    Ok(tokens)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+impl BinaryOp {
+    fn apply(self, a: f32, b: f32) -> f32 {
+        match self {
+            Self::Add => a + b,
+            Self::Sub => a - b,
+            Self::Mul => a * b,
+            Self::Div => a / b,
+            Self::Rem => a % b,
+            Self::Pow => a.powf(b),
+        }
+    }
+
+    /// The operator of an infix token, with how strongly it binds to its left and right.
+    /// A higher left than right power makes it right associative.
+    fn infix(kind: TokenKind) -> Option<(Self, u8, u8)> {
+        Some(match kind {
+            TokenKind::Plus => (Self::Add, 1, 2),
+            TokenKind::Minus => (Self::Sub, 1, 2),
+            TokenKind::Star => (Self::Mul, 3, 4),
+            TokenKind::Slash => (Self::Div, 3, 4),
+            TokenKind::Percent => (Self::Rem, 3, 4),
+            TokenKind::Caret => (Self::Pow, 7, 6),
+            _ => return None,
+        })
+    }
+}
+
+/// How strongly a leading `-` binds, tighter than `*` but looser than `^`, so `-x^2` is
+/// `-(x^2)`.
+const PREFIX_POWER: u8 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Sinh,
+    Cosh,
+    Tanh,
+    Sqrt,
+    Exp,
+    Ln,
+    Abs,
+    Sign,
+    Floor,
+    Ceil,
+    Min,
+    Max,
+    Pow,
+}
+
+impl Function {
+    pub const ALL: [Self; 20] = [
+        Self::Sin,
+        Self::Cos,
+        Self::Tan,
+        Self::Asin,
+        Self::Acos,
+        Self::Atan,
+        Self::Atan2,
+        Self::Sinh,
+        Self::Cosh,
+        Self::Tanh,
+        Self::Sqrt,
+        Self::Exp,
+        Self::Ln,
+        Self::Abs,
+        Self::Sign,
+        Self::Floor,
+        Self::Ceil,
+        Self::Min,
+        Self::Max,
+        Self::Pow,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sin => "sin",
+            Self::Cos => "cos",
+            Self::Tan => "tan",
+            Self::Asin => "asin",
+            Self::Acos => "acos",
+            Self::Atan => "atan",
+            Self::Atan2 => "atan2",
+            Self::Sinh => "sinh",
+            Self::Cosh => "cosh",
+            Self::Tanh => "tanh",
+            Self::Sqrt => "sqrt",
+            Self::Exp => "exp",
+            Self::Ln => "ln",
+            Self::Abs => "abs",
+            Self::Sign => "sign",
+            Self::Floor => "floor",
+            Self::Ceil => "ceil",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Pow => "pow",
+        }
+    }
+
+    pub fn arity(self) -> usize {
+        match self {
+            Self::Atan2 | Self::Min | Self::Max | Self::Pow => 2,
+            _ => 1,
+        }
+    }
+
+    /// `args` has [`Self::arity`] values.
+    fn apply(self, args: &[f32]) -> f32 {
+        let x = args[0];
+        match self {
+            Self::Sin => x.sin(),
+            Self::Cos => x.cos(),
+            Self::Tan => x.tan(),
+            Self::Asin => x.asin(),
+            Self::Acos => x.acos(),
+            Self::Atan => x.atan(),
+            Self::Atan2 => x.atan2(args[1]),
+            Self::Sinh => x.sinh(),
+            Self::Cosh => x.cosh(),
+            Self::Tanh => x.tanh(),
+            Self::Sqrt => x.sqrt(),
+            Self::Exp => x.exp(),
+            Self::Ln => x.ln(),
+            Self::Abs => x.abs(),
+            // Unlike `f32::signum`, zero has no sign.
+            Self::Sign => {
+                if x == 0.0 {
+                    0.0
+                } else {
+                    x.signum()
+                }
+            }
+            Self::Floor => x.floor(),
+            Self::Ceil => x.ceil(),
+            Self::Min => x.min(args[1]),
+            Self::Max => x.max(args[1]),
+            Self::Pow => x.powf(args[1]),
+        }
+    }
+}
+
+/// A parsed expression, with its constant parts already folded.
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Number(f32),
+    /// Index into the variables.
+    Variable(usize),
+    Neg(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+impl Node {
+    fn neg(operand: Self) -> Self {
+        match operand {
+            Self::Number(x) => Self::Number(-x),
+            operand => Self::Neg(Box::new(operand)),
+        }
+    }
+
+    fn binary(op: BinaryOp, a: Self, b: Self) -> Self {
+        match (a, b) {
+            (Self::Number(a), Self::Number(b)) => Self::Number(op.apply(a, b)),
+            (a, b) => Self::Binary(op, Box::new(a), Box::new(b)),
+        }
+    }
+
+    fn call(function: Function, args: Vec<Self>) -> Self {
+        let constants: Option<Vec<f32>> = args
+            .iter()
+            .map(|arg| match arg {
+                Self::Number(x) => Some(*x),
+                _ => None,
+            })
+            .collect();
+        match constants {
+            Some(values) => Self::Number(function.apply(&values)),
+            None => Self::Call(function, args),
+        }
+    }
+
+    /// Append the bytecode computing this node, returning how many values it stacks up at
+    /// most.
+    fn compile(&self, code: &mut Vec<Op>) -> usize {
+        match self {
+            Self::Number(x) => {
+                code.push(Op::Number(*x));
+                1
+            }
+            Self::Variable(i) => {
+                code.push(Op::Variable(*i));
+                1
+            }
+            Self::Neg(operand) => {
+                let depth = operand.compile(code);
+                code.push(Op::Neg);
+                depth
+            }
+            Self::Binary(op, a, b) => {
+                let depth = a.compile(code).max(1 + b.compile(code));
+                code.push(Op::Binary(*op));
+                depth
+            }
+            Self::Call(function, args) => {
+                let depth = args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| i + arg.compile(code))
+                    .max()
+                    .unwrap_or(0);
+                code.push(Op::Call(*function));
+                depth
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<Token>,
+    next: usize,
+    variables: &'a [&'a str],
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.next].clone();
+        // The last token is `End`, which is never consumed.
+        if token.kind != TokenKind::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn source(&self, span: &Span) -> &'a str {
+        &self.text[span.clone()]
+    }
+
+    /// Parse operators binding at least as strongly as `min_power`.
+    fn expression(&mut self, min_power: u8) -> Result<Node, ParseError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(ParseError::new(
+                "too deeply nested",
+                self.peek().span.clone(),
+            ));
+        }
+
+        let mut node = self.operand()?;
+        while let Some((op, left, right)) = BinaryOp::infix(self.peek().kind) {
+            if left < min_power {
+                break;
+            }
+            self.advance();
+            let rhs = self.expression(right)?;
+            node = Node::binary(op, node, rhs);
+        }
+
+        self.depth -= 1;
+        Ok(node)
+    }
+
+    /// A number, name, call, parenthesized expression or a signed one of those.
+    fn operand(&mut self) -> Result<Node, ParseError> {
+        let token = self.advance();
+        match token.kind {
+            TokenKind::Number(x) => Ok(Node::Number(x)),
+            TokenKind::Minus => Ok(Node::neg(self.expression(PREFIX_POWER)?)),
+            TokenKind::Plus => self.expression(PREFIX_POWER),
+            TokenKind::Open => {
+                let node = self.expression(0)?;
+                match self.peek().kind {
+                    TokenKind::Close => {
+                        self.advance();
+                        Ok(node)
+                    }
+                    _ => Err(self.unexpected(Some(token.span))),
+                }
+            }
+            TokenKind::Name if self.peek().kind == TokenKind::Open => self.call(token.span),
+            TokenKind::Name => self.name(token.span),
+            TokenKind::End => Err(ParseError::new(
+                "expected a number, variable or `(` at the end",
+                token.span,
+            )),
+            _ => Err(ParseError::new(
+                format!(
+                    "expected a number, variable or `(`, not `{}`",
+                    self.source(&token.span)
+                ),
+                token.span,
+            )),
+        }
+    }
+
+    /// The error for the next token, which doesn't continue the expression before it, inside
+    /// the parenthesis opened at `open`.
+    fn unexpected(&self, open: Option<Span>) -> ParseError {
+        let token = self.peek();
+        let text = self.source(&token.span);
+        match (token.kind, open) {
+            (TokenKind::End, Some(open)) => ParseError::new("this `(` is never closed", open),
+            (TokenKind::Close, None) => ParseError::new("unmatched `)`", token.span.clone()),
+            (TokenKind::Number(_) | TokenKind::Name | TokenKind::Open, _) => ParseError::new(
+                format!("expected an operator like `*` before `{text}`"),
+                token.span.clone(),
+            ),
+            _ => ParseError::new(format!("unexpected `{text}`"), token.span.clone()),
+        }
+    }
+
+    fn name(&self, span: Span) -> Result<Node, ParseError> {
+        let name = self.source(&span);
+        if let Some(i) = self.variables.iter().position(|&v| v == name) {
+            return Ok(Node::Variable(i));
+        }
+        if let Some(&(_, value)) = CONSTANTS.iter().find(|(c, _)| *c == name) {
+            return Ok(Node::Number(value));
+        }
+        if Function::ALL.iter().any(|f| f.name() == name) {
+            return Err(ParseError::new(
+                format!("`{name}` is a function, call it like `{name}(…)`"),
+                span,
+            ));
+        }
+        let message = match self.variables {
+            [] => format!("unknown name `{name}`, there are no variables"),
+            [only] => format!("unknown name `{name}`, the variable is {only}"),
+            [rest @ .., last] => format!(
+                "unknown name `{name}`, the variables are {} and {last}",
+                rest.join(", ")
+            ),
+        };
+        Err(ParseError::new(message, span))
+    }
+
+    /// A function call, from its name up to the `(`.
+    fn call(&mut self, name: Span) -> Result<Node, ParseError> {
+        let function = Function::ALL
+            .into_iter()
+            .find(|f| f.name() == self.source(&name))
+            .ok_or_else(|| {
+                ParseError::new(
+                    format!("unknown function `{}`", self.source(&name)),
+                    name.clone(),
+                )
+            })?;
+        let open = self.advance().span;
+
+        let mut args = Vec::new();
+        if self.peek().kind != TokenKind::Close {
+            loop {
+                args.push(self.expression(0)?);
+                match self.peek().kind {
+                    TokenKind::Comma => {
+                        self.advance();
+                    }
+                    TokenKind::Close => break,
+                    _ => return Err(self.unexpected(Some(open))),
+                }
+            }
+        }
+        let close = self.advance().span;
+
+        if args.len() != function.arity() {
+            let plural = if function.arity() == 1 { "" } else { "s" };
+            return Err(ParseError::new(
+                format!(
+                    "`{}` takes {} argument{plural}, not {}",
+                    function.name(),
+                    function.arity(),
+                    args.len()
+                ),
+                name.start..close.end,
+            ));
+        }
+        Ok(Node::call(function, args))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Number(f32),
+    Variable(usize),
+    Neg,
+    Binary(BinaryOp),
+    Call(Function),
+}
+
+/// An expression compiled to run over the values of its variables.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    code: Vec<Op>,
+}
+
+impl Expression {
+    /// Parse `text`, which may use the `variables` by name, standard functions like `sin`,
+    /// and `pi`, `tau` and `e`.
+    pub fn parse(text: &str, variables: &[&str]) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            text,
+            tokens: tokenize(text)?,
+            next: 0,
+            variables,
+            depth: 0,
+        };
+        let node = parser.expression(0)?;
+
+        if parser.peek().kind != TokenKind::End {
+            return Err(parser.unexpected(None));
+        }
+
+        let mut code = Vec::new();
+        if node.compile(&mut code) > MAX_DEPTH {
+            return Err(ParseError::new("too deeply nested", 0..text.len()));
+        }
+        Ok(Self { code })
+    }
+
+    /// The value for the `variables` in the order they were given to [`Self::parse`].
+    pub fn eval(&self, variables: &[f32]) -> f32 {
+        let mut stack = [0.0; MAX_DEPTH];
+        let mut top = 0;
+        for op in &self.code {
+            match *op {
+                Op::Number(x) => {
+                    stack[top] = x;
+                    top += 1;
+                }
+                Op::Variable(i) => {
+                    stack[top] = variables[i];
+                    top += 1;
+                }
+                Op::Neg => stack[top - 1] = -stack[top - 1],
+                Op::Binary(op) => {
+                    top -= 1;
+                    stack[top - 1] = op.apply(stack[top - 1], stack[top]);
+                }
+                Op::Call(function) => {
+                    top -= function.arity();
+                    stack[top] = function.apply(&stack[top..]);
+                    top += 1;
+                }
+            }
+        }
+        stack[0]
+    }
+}
+
+/// A single line editor for an expression, with the span of `error` highlighted.
+pub fn expression_edit(
+    ui: &mut egui::Ui,
+    text: &mut String,
+    error: Option<&ParseError>,
+    width: f32,
+) -> egui::Response {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let color = ui.visuals().text_color();
+    let error_color = ui.visuals().error_fg_color;
+    let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+        let normal = egui::TextFormat::simple(font_id.clone(), color);
+        let mut job = egui::text::LayoutJob::default();
+        // The error is from the last parse, the text may have changed since.
+        match error.and_then(|err| highlighted(text, err.span.clone())) {
+            Some(span) => {
+                job.append(&text[..span.start], 0.0, normal.clone());
+                job.append(
+                    &text[span.clone()],
+                    0.0,
+                    egui::TextFormat {
+                        background: error_color.gamma_multiply(0.3),
+                        underline: egui::Stroke::new(1.5, error_color),
+                        ..normal.clone()
+                    },
+                );
+                job.append(&text[span.end..], 0.0, normal);
+            }
+            None => job.append(text, 0.0, normal),
+        }
+        job.wrap.max_width = wrap_width;
+        ui.fonts(|fonts| fonts.layout_job(job))
+    };
+    ui.add(
+        egui::TextEdit::singleline(text)
+            .desired_width(width)
+            .layouter(&mut layouter),
+    )
+}
+
+/// The part of `text` to highlight for an error at `span`, the last character for an error
+/// at the end, or `None` when the span doesn't fit the text.
+fn highlighted(text: &str, span: Span) -> Option<Span> {
+    text.get(span.clone())?;
+    if !span.is_empty() {
+        return Some(span);
+    }
+    let last = text[..span.start].chars().next_back()?;
+    Some(span.start - last.len_utf8()..span.start)
+}
+
+#[test]
+fn test_parse_and_eval() {
+    let eval = |text: &str| {
+        Expression::parse(text, &["x", "y", "t"])
+            .unwrap_or_else(|err| panic!("{text}: {err:?}"))
+            .eval(&[2.0, 3.0, 0.5])
+    };
+    assert_eq!(eval("x^2 - y*t + sin(t)"), 4.0 - 1.5 + 0.5f32.sin());
+    assert_eq!(eval("1 + 2 * 3"), 7.0);
+    assert_eq!(eval("(1 + 2) * 3"), 9.0);
+    assert_eq!(eval("8 / 4 / 2"), 1.0);
+    assert_eq!(eval("2 ^ 3 ^ 2"), 512.0);
+    assert_eq!(eval("-x^2"), -4.0);
+    assert_eq!(eval("-2 * -y"), 6.0);
+    assert_eq!(eval("x ^ -1"), 0.5);
+    assert_eq!(eval("7 % 4 + +1"), 4.0);
+    assert_eq!(eval("1.5e1 + .5 + 2.5E-1 + 1e+1"), 25.75);
+    assert_eq!(eval("x² + y³"), 31.0);
+    assert_eq!(eval("max(x, y) - min(x, y) * atan2(0, 1)"), 3.0);
+    assert_eq!(eval("pow(y, x) + abs(-t) + sign(0)"), 9.5);
+    assert_eq!(eval("x × y − t"), 5.5);
+    assert_eq!(eval("cos(pi) + floor(e) + ceil(tau)"), 8.0);
+
+    // Variables win over constants of the same name.
+    let e = Expression::parse("e * 2", &["e"]).unwrap();
+    assert_eq!(e.eval(&[10.0]), 20.0);
+}
+
+#[test]
+fn test_constant_folding() {
+    let code = |text: &str| Expression::parse(text, &["x"]).unwrap().code;
+    assert_eq!(code("2 * 3 + 1"), [Op::Number(7.0)]);
+    assert_eq!(code("-(sqrt(16) - 1)"), [Op::Number(-3.0)]);
+    assert_eq!(code("cos(0) * x"), code("1 * x"));
+    assert_eq!(
+        code("x * (2 + 3)"),
+        [Op::Variable(0), Op::Number(5.0), Op::Binary(BinaryOp::Mul)]
+    );
+
+    // Deep but left associative chains need little stack.
+    let long = vec!["x"; 1000].join(" + ");
+    let sum = Expression::parse(&long, &["x"]).unwrap();
+    assert_eq!(sum.eval(&[1.0]), 1000.0);
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = |text: &str| {
+        let err = Expression::parse(text, &["x", "y", "t"]).unwrap_err();
+        (err.message, text[err.span].to_owned())
+    };
+    assert_eq!(error("x + z").1, "z");
+    assert!(
+        error("x + z").0.contains("x, y and t"),
+        "{:?}",
+        error("x + z")
+    );
+    assert_eq!(error("x +").1, "");
+    assert_eq!(error("x * * y").1, "*");
+    assert_eq!(error("2x").1, "x");
+    assert!(error("2x").0.contains("operator"));
+    assert_eq!(error("(x + 1").1, "(");
+    assert_eq!(error("x + 1)").1, ")");
+    assert_eq!(error("sin(x, y) + 1").1, "sin(x, y)");
+    assert_eq!(error("foo(x)").1, "foo");
+    assert_eq!(error("sin + 1").1, "sin");
+    assert_eq!(error("x # 2").1, "#");
+    assert_eq!(error("1.2.3").1, "1.2.3");
+    assert_eq!(error("max(x y)").1, "y");
+    assert_eq!(error("max(x,").1, "");
+    assert_eq!(error("(x + 1, 2)").1, ",");
+    assert_eq!(error(&"(".repeat(100)).0, "too deeply nested");
+    assert_eq!(
+        error(&format!("{}x", "-".repeat(100))).0,
+        "too deeply nested"
+    );
+
+    let err = Expression::parse("x + é", &["x"]).unwrap_err();
+    assert_eq!((err.span.clone(), err.column("x + é")), (4..6, 5));
+
+    // An error at the end highlights the character before it.
+    assert_eq!(highlighted("x +", 3..3), Some(2..3));
+    assert_eq!(highlighted("x + é", 2..3), Some(2..3));
+    assert_eq!(highlighted("x", 0..5), None);
+}
@@ -1,7 +1,34 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+mod accessibility;
 mod app;
+mod attractor;
+mod audio;
+mod audio_reactive;
+mod audio_source;
+mod background;
+mod background_config;
+mod background_mesh;
+mod chaos;
+mod chart;
+mod contact;
+mod content;
+mod content_schema;
+mod content_source;
+pub mod dsp;
 mod easy_mark_parser;
 mod easy_mark_viewer;
+mod editor;
+mod expression;
+mod market;
+mod pointer;
+mod quality;
+mod rain;
 
 pub use app::PortfolioApp;
+
+// Only for `benches/background.rs`.
+#[cfg(feature = "bench")]
+pub use background::{Background, NeighborSearch};
+#[cfg(feature = "bench")]
+pub use background_config::BoundaryMode;
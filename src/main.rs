@@ -15,7 +15,6 @@ fn main() -> eframe::Result<()> {
                 eframe::icon_data::from_png_bytes(&include_bytes!("../assets/icon-256.png")[..])
                     .unwrap(),
             ),
-        vsync: false,
         ..Default::default()
     };
     eframe::run_native(
@@ -0,0 +1,256 @@
+//! A made up stock market for the [`crate::chart`], generated from a seed so it works offline
+//! and looks the same every time.
+//!
+//! Daily returns follow a random walk whose volatility clusters like real markets do
+//! (GARCH(1, 1)), and every day is walked through in ticks to find its high and low.
+
+use rand::prelude::*;
+
+/// Days since 1970-01-01 of the first candle, 2020-01-02.
+const FIRST_DAY: i32 = 18_263;
+
+const START_PRICE: f32 = 100.0;
+
+/// Average daily log return, about 8 % a year.
+const DRIFT: f64 = 0.0003;
+
+/// GARCH(1, 1) weights of the last squared return and the last variance. The rest pulls the
+/// variance back to [`LONG_RUN_VOLATILITY`].
+const GARCH_ALPHA: f64 = 0.08;
+const GARCH_BETA: f64 = 0.9;
+const LONG_RUN_VOLATILITY: f64 = 0.015;
+
+/// Steps every day is walked through, and how much of a day's volatility is in its opening gap.
+const TICKS: usize = 32;
+const GAP: f64 = 0.2;
+
+/// Shares traded on an average day.
+const BASE_VOLUME: f32 = 1_000_000.0;
+
+/// One day of trading.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Candle {
+    /// Days since 1970-01-01.
+    pub day: i32,
+    pub open: f32,
+    pub high: f32,
+    pub low: f32,
+    pub close: f32,
+    pub volume: f32,
+}
+
+impl Candle {
+    pub fn rising(&self) -> bool {
+        self.close >= self.open
+    }
+}
+
+/// `days` trading days from 2020 on, the same for the same `seed`.
+pub fn generate(seed: u64, days: usize) -> Vec<Candle> {
+    let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(seed);
+    let mut normal = move || {
+        // Box-Muller, 1 - u keeps the logarithm finite.
+        let u: f64 = 1.0 - rng.gen::<f64>();
+        let v: f64 = rng.gen();
+        (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+    };
+
+    let long_run_variance = LONG_RUN_VOLATILITY * LONG_RUN_VOLATILITY;
+    let omega = long_run_variance * (1.0 - GARCH_ALPHA - GARCH_BETA);
+    let mut variance = long_run_variance;
+    let mut close = START_PRICE as f64;
+    let mut day = FIRST_DAY;
+
+    let mut candles = Vec::with_capacity(days);
+    for _ in 0..days {
+        let volatility = variance.sqrt();
+        let open = close * (volatility * GAP * normal()).exp();
+        let (mut price, mut high, mut low) = (open, open, open);
+        let tick_volatility = volatility * (1.0 - GAP * GAP).sqrt() / (TICKS as f64).sqrt();
+        for _ in 0..TICKS {
+            price *= (DRIFT / TICKS as f64 + tick_volatility * normal()).exp();
+            high = high.max(price);
+            low = low.min(price);
+        }
+
+        let log_return = (price / close).ln();
+        // Busy days are the ones that move.
+        let surprise = (log_return / volatility).abs() as f32;
+        let volume = BASE_VOLUME * (0.3 * normal() as f32).exp() * (0.6 + 0.5 * surprise);
+
+        candles.push(Candle {
+            day,
+            open: open as f32,
+            high: high as f32,
+            low: low as f32,
+            close: price as f32,
+            volume,
+        });
+
+        variance = omega + GARCH_ALPHA * log_return * log_return + GARCH_BETA * variance;
+        close = price;
+        day = next_trading_day(day);
+    }
+    candles
+}
+
+/// The weekday after `day`, skipping Saturday and Sunday.
+fn next_trading_day(day: i32) -> i32 {
+    let mut next = day + 1;
+    while weekday(next) >= 5 {
+        next += 1;
+    }
+    next
+}
+
+/// 0 for Monday to 6 for Sunday, 1970-01-01 was a Thursday.
+fn weekday(day: i32) -> i32 {
+    (day + 3).rem_euclid(7)
+}
+
+/// Year, month and day of the month of `day` days since 1970-01-01.
+///
+/// From Howard Hinnant's `civil_from_days`, counting in 400 year eras starting in March so the
+/// leap day is the last of the year.
+pub fn civil_date(day: i32) -> (i32, u32, u32) {
+    let z = day + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day_of_month = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i32::from(month <= 2);
+    (year, month, day_of_month)
+}
+
+/// `day` as `2020-01-02`.
+pub fn format_date(day: i32) -> String {
+    let (year, month, day) = civil_date(day);
+    format!("{year}-{month:02}-{day:02}")
+}
+
+/// A line following the closing prices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Average {
+    /// Mean of the last closes.
+    Simple(usize),
+    /// Weighing recent closes more, with a smoothing of 2 / (days + 1).
+    Exponential(usize),
+}
+
+impl Average {
+    pub fn name(self) -> String {
+        match self {
+            Self::Simple(days) => format!("SMA {days}"),
+            Self::Exponential(days) => format!("EMA {days}"),
+        }
+    }
+
+    /// The average at every candle, `None` until there are enough days before it.
+    pub fn values(self, candles: &[Candle]) -> Vec<Option<f32>> {
+        match self {
+            Self::Simple(days) => {
+                let mut sum = 0.0;
+                candles
+                    .iter()
+                    .enumerate()
+                    .map(|(i, candle)| {
+                        sum += candle.close as f64;
+                        if i >= days {
+                            sum -= candles[i - days].close as f64;
+                        }
+                        (i + 1 >= days).then_some((sum / days as f64) as f32)
+                    })
+                    .collect()
+            }
+            Self::Exponential(days) => {
+                // Starts from the simple average of the first days.
+                let smoothing = 2.0 / (days as f32 + 1.0);
+                let mut average = None;
+                let simple = Self::Simple(days).values(candles);
+                candles
+                    .iter()
+                    .zip(simple)
+                    .map(|(candle, simple)| {
+                        average = match average {
+                            Some(last) => Some(last + smoothing * (candle.close - last)),
+                            None => simple,
+                        };
+                        average
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[test]
+fn test_generate() {
+    let candles = generate(7, 500);
+    assert_eq!(candles.len(), 500);
+    assert_eq!(candles, generate(7, 500));
+    assert_ne!(candles, generate(8, 500));
+
+    for pair in candles.windows(2) {
+        assert!(pair[1].day > pair[0].day);
+    }
+    for candle in &candles {
+        assert!(candle.low <= candle.open.min(candle.close), "{candle:?}");
+        assert!(candle.high >= candle.open.max(candle.close), "{candle:?}");
+        assert!(candle.low > 0.0 && candle.volume > 0.0, "{candle:?}");
+        assert!(weekday(candle.day) < 5, "{candle:?}");
+    }
+
+    // Moves of a few percent a day, like a stock.
+    let returns: Vec<f32> = candles
+        .windows(2)
+        .map(|pair| (pair[1].close / pair[0].close).ln())
+        .collect();
+    let volatility = (returns.iter().map(|r| r * r).sum::<f32>() / returns.len() as f32).sqrt();
+    assert!((0.008..0.03).contains(&volatility), "{volatility}");
+}
+
+#[test]
+fn test_dates() {
+    assert_eq!(format_date(0), "1970-01-01");
+    assert_eq!(format_date(FIRST_DAY), "2020-01-02");
+    assert_eq!(format_date(FIRST_DAY + 58), "2020-02-29");
+    assert_eq!(format_date(-1), "1969-12-31");
+    assert_eq!(civil_date(11_016), (2000, 2, 29));
+    assert_eq!(weekday(FIRST_DAY), 3);
+    // From Friday 2020-01-03 to Monday 2020-01-06.
+    assert_eq!(format_date(next_trading_day(FIRST_DAY + 1)), "2020-01-06");
+}
+
+#[test]
+fn test_averages() {
+    let candles: Vec<Candle> = [1.0, 2.0, 3.0, 4.0, 5.0]
+        .into_iter()
+        .enumerate()
+        .map(|(day, close)| Candle {
+            day: day as i32,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1.0,
+        })
+        .collect();
+    assert_eq!(
+        Average::Simple(3).values(&candles),
+        [None, None, Some(2.0), Some(3.0), Some(4.0)]
+    );
+    // Smoothing 0.5: from 2 to 3 to 4.
+    assert_eq!(
+        Average::Exponential(3).values(&candles),
+        [None, None, Some(2.0), Some(3.0), Some(4.0)]
+    );
+    assert_eq!(Average::Simple(6).values(&candles), [None; 5]);
+}
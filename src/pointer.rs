@@ -0,0 +1,176 @@
+//! The mouse and fingers interacting with the [`crate::Background`].
+
+use std::collections::BTreeMap;
+
+use eframe::egui;
+
+/// A mouse pointer or a finger on the screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Pointer {
+    pub pos: egui::Pos2,
+
+    /// In points per second.
+    pub velocity: egui::Vec2,
+
+    /// The primary button is held, or the finger touches the screen.
+    pub down: bool,
+
+    /// Went down since the last frame.
+    pub pressed: bool,
+}
+
+impl Pointer {
+    /// A pointer hovering at `pos` without moving.
+    #[cfg(test)]
+    pub fn at(pos: egui::Pos2) -> Self {
+        Self {
+            pos,
+            ..Default::default()
+        }
+    }
+}
+
+/// Turns the input of every frame into the [`Pointer`]s that act on the background.
+///
+/// Every finger touching the screen is its own pointer. Without touches it is the mouse, or no
+/// pointer at all when the mouse is outside the window.
+#[derive(Default)]
+pub struct PointerTracker {
+    /// Fingers on the screen by device and touch id.
+    touches: BTreeMap<(u64, u64), Pointer>,
+}
+
+impl PointerTracker {
+    pub fn pointers(&mut self, ctx: &egui::Context) -> Vec<Pointer> {
+        ctx.input(|input| {
+            let dt = input.unstable_dt.max(1e-3);
+            // Where every finger was when the frame started, the velocity is the whole way it
+            // moved since, however many move events that took.
+            let mut start: BTreeMap<(u64, u64), egui::Pos2> = BTreeMap::new();
+            for (&key, touch) in &mut self.touches {
+                touch.pressed = false;
+                touch.velocity = egui::Vec2::ZERO;
+                start.insert(key, touch.pos);
+            }
+            for event in &input.events {
+                let egui::Event::Touch {
+                    device_id,
+                    id,
+                    phase,
+                    pos,
+                    ..
+                } = *event
+                else {
+                    continue;
+                };
+                let key = (device_id.0, id.0);
+                match phase {
+                    egui::TouchPhase::Start => {
+                        start.insert(key, pos);
+                        self.touches.insert(
+                            key,
+                            Pointer {
+                                pos,
+                                velocity: egui::Vec2::ZERO,
+                                down: true,
+                                pressed: true,
+                            },
+                        );
+                    }
+                    egui::TouchPhase::Move => {
+                        if let Some(touch) = self.touches.get_mut(&key) {
+                            touch.pos = pos;
+                        }
+                    }
+                    egui::TouchPhase::End | egui::TouchPhase::Cancel => {
+                        self.touches.remove(&key);
+                    }
+                }
+            }
+            for (key, touch) in &mut self.touches {
+                if let Some(&start) = start.get(key) {
+                    touch.velocity = (touch.pos - start) / dt;
+                }
+            }
+        });
+
+        // Windows and widgets keep what happens on them to themselves.
+        let over_ui = |pos| ctx.layer_id_at(pos).is_some();
+
+        if !self.touches.is_empty() {
+            let using_ui = ctx.is_using_pointer();
+            return self
+                .touches
+                .values()
+                .map(|&touch| {
+                    let on_background = !using_ui && !over_ui(touch.pos);
+                    Pointer {
+                        down: touch.down && on_background,
+                        pressed: touch.pressed && on_background,
+                        ..touch
+                    }
+                })
+                .collect();
+        }
+
+        let on_background = !ctx.wants_pointer_input();
+        ctx.input(|input| {
+            let pointer = &input.pointer;
+            pointer
+                .hover_pos()
+                .map(|pos| Pointer {
+                    pos,
+                    velocity: pointer.velocity(),
+                    down: pointer.primary_down() && on_background,
+                    pressed: pointer.primary_pressed() && on_background,
+                })
+                .into_iter()
+                .collect()
+        })
+    }
+}
+
+#[test]
+fn test_touch_velocity() {
+    let ctx = egui::Context::default();
+    let mut tracker = PointerTracker::default();
+    let mut frame = |time: f64, events: Vec<egui::Event>| {
+        let input = egui::RawInput {
+            time: Some(time),
+            events,
+            ..Default::default()
+        };
+        let mut pointers = Vec::new();
+        let _ = ctx.run(input, |ctx| pointers = tracker.pointers(ctx));
+        pointers
+    };
+    let touch = |phase, x: f32| egui::Event::Touch {
+        device_id: egui::TouchDeviceId(0),
+        id: egui::TouchId(1),
+        phase,
+        pos: egui::pos2(x, 100.0),
+        force: None,
+    };
+
+    let pointers = frame(0.0, vec![touch(egui::TouchPhase::Start, 100.0)]);
+    assert_eq!(pointers.len(), 1);
+    assert_eq!(pointers[0].velocity, egui::Vec2::ZERO);
+
+    // Two moves in one frame: 20 points in a tenth of a second.
+    let pointers = frame(
+        0.1,
+        vec![
+            touch(egui::TouchPhase::Move, 105.0),
+            touch(egui::TouchPhase::Move, 120.0),
+        ],
+    );
+    assert_eq!(pointers[0].pos, egui::pos2(120.0, 100.0));
+    assert!((pointers[0].velocity - egui::vec2(200.0, 0.0)).length() < 0.1);
+
+    // Held still, the finger doesn't keep its last velocity.
+    let pointers = frame(0.2, Vec::new());
+    assert_eq!(pointers[0].pos, egui::pos2(120.0, 100.0));
+    assert_eq!(pointers[0].velocity, egui::Vec2::ZERO);
+
+    assert!(frame(0.3, vec![touch(egui::TouchPhase::End, 120.0)]).is_empty());
+}
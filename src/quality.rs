@@ -0,0 +1,165 @@
+//! How much work the [`crate::Background`] may do: a governor scaling it down when frames get
+//! slow, and pausing it when nobody is looking.
+
+use eframe::egui;
+
+use crate::accessibility::Motion;
+
+/// CPU time a frame may take before the background gets scaled down. Well under the 1/60 s of a
+/// frame at 60 Hz, the browser and GPU need their share too.
+const FRAME_BUDGET: f32 = 0.008;
+
+/// The governor never scales the background below this.
+const MIN_SCALE: f32 = 0.2;
+
+/// Frames to wait after changing the scale before judging it, so the average catches up.
+const COOLDOWN_FRAMES: u32 = 30;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Quality {
+    /// Scale the background down when frames take longer than [`FRAME_BUDGET`].
+    pub adaptive: bool,
+
+    /// Stop the background while the window is unfocused, minimized or in a hidden tab.
+    pub pause_when_unfocused: bool,
+
+    /// Fraction of the configured points and link distance the background runs with.
+    #[serde(skip)]
+    scale: f32,
+
+    /// Moving average of the CPU time of recent frames, in seconds.
+    #[serde(skip)]
+    frame_time: Option<f32>,
+
+    #[serde(skip)]
+    cooldown: u32,
+
+    /// The background stood still last frame.
+    #[serde(skip)]
+    paused: bool,
+}
+
+impl Default for Quality {
+    fn default() -> Self {
+        Self {
+            adaptive: true,
+            pause_when_unfocused: true,
+            scale: 1.0,
+            frame_time: None,
+            cooldown: 0,
+            paused: false,
+        }
+    }
+}
+
+impl Quality {
+    /// Fraction of the configured points and link distance the background should run with.
+    pub fn scale(&self) -> f32 {
+        if self.adaptive {
+            self.scale
+        } else {
+            1.0
+        }
+    }
+
+    /// Feed the CPU time of the last frame, from [`eframe::Frame::info`].
+    ///
+    /// Frames over budget shrink the scale, frames well within it grow it back.
+    pub fn record_frame_time(&mut self, cpu_usage: Option<f32>) {
+        let Some(cpu_usage) = cpu_usage else {
+            return;
+        };
+        let average = self
+            .frame_time
+            .map_or(cpu_usage, |average| average * 0.9 + cpu_usage * 0.1);
+        self.frame_time = Some(average);
+
+        if !self.adaptive {
+            return;
+        }
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return;
+        }
+        let scale = if average > FRAME_BUDGET {
+            (self.scale * 0.8).max(MIN_SCALE)
+        } else if average < FRAME_BUDGET / 2.0 {
+            (self.scale * 1.1).min(1.0)
+        } else {
+            self.scale
+        };
+        if scale != self.scale {
+            self.scale = scale;
+            self.cooldown = COOLDOWN_FRAMES;
+        }
+    }
+
+    /// How far to advance the background this frame at the speed of `motion`, `None` while it
+    /// should stand still.
+    ///
+    /// The first frame after a pause advances by nothing, rather than catching up on the pause.
+    pub fn simulation_dt(&mut self, ctx: &egui::Context, motion: Motion) -> Option<f32> {
+        let hidden = self.pause_when_unfocused && !is_visible(ctx);
+        let was_paused = self.paused;
+        let speed = motion.speed().filter(|_| !hidden);
+        self.paused = speed.is_none();
+        let speed = speed?;
+        if was_paused {
+            Some(0.0)
+        } else {
+            Some(ctx.input(|input| input.unstable_dt) * speed)
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.pause_when_unfocused, "Pause when unfocused");
+        ui.checkbox(&mut self.adaptive, "Adapt to slow frames");
+        if self.adaptive {
+            let frame_time = self.frame_time.unwrap_or_default() * 1000.0;
+            ui.weak(format!(
+                "Running at {:.0}% quality, frames take {frame_time:.1} ms",
+                self.scale * 100.0
+            ));
+        }
+    }
+}
+
+/// Whether anyone can see the window: focused, not minimized, and on the web not in a
+/// background tab.
+fn is_visible(ctx: &egui::Context) -> bool {
+    let shown = ctx.input(|input| input.focused && input.viewport().minimized != Some(true));
+
+    #[cfg(not(target_arch = "wasm32"))]
+    return shown;
+
+    #[cfg(target_arch = "wasm32")]
+    return shown
+        && !web_sys::window()
+            .and_then(|window| window.document())
+            .map_or(false, |document| document.hidden());
+}
+
+#[test]
+fn test_governor() {
+    let mut quality = Quality::default();
+    let run = |quality: &mut Quality, frame_time: f32| {
+        for _ in 0..1000 {
+            quality.record_frame_time(Some(frame_time));
+        }
+    };
+
+    run(&mut quality, 0.030);
+    assert_eq!(quality.scale(), MIN_SCALE);
+
+    // Within budget, but not by enough to grow back.
+    run(&mut quality, 0.006);
+    assert_eq!(quality.scale(), MIN_SCALE);
+
+    run(&mut quality, 0.002);
+    assert_eq!(quality.scale(), 1.0);
+
+    quality.adaptive = false;
+    run(&mut quality, 0.030);
+    assert_eq!(quality.scale(), 1.0);
+}
@@ -0,0 +1,406 @@
+//! The rain scene: drops falling on the [`crate::Background`] with the sound of rain and
+//! thunder, made by shaping noise in the frequency domain.
+
+use std::sync::{Arc, Mutex};
+
+use eframe::egui;
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+
+use crate::audio::{AudioOutput, Synth};
+use crate::background::Background;
+use crate::background_config::BackgroundConfig;
+use crate::dsp::{self, Complex, OverlapAdd, Window};
+
+/// Samples per generated block, the resolution of the shaped spectrum.
+const BLOCK_SIZE: usize = 1024;
+
+/// Blocks overlap by half, every hop starts a new one.
+const HOP_SIZE: usize = BLOCK_SIZE / 2;
+
+/// Center of the hiss of the rain, in Hz.
+const RAIN_CENTER: f32 = 3000.0;
+
+/// Width of the hiss in octaves, as a standard deviation.
+const RAIN_WIDTH: f32 = 1.0;
+
+/// Frequency in Hz above which the rumble of thunder falls off.
+const THUNDER_CUTOFF: f32 = 80.0;
+
+/// RMS of the rain at full intensity and volume, and of the loudest thunder.
+const RAIN_RMS: f32 = 0.2;
+const THUNDER_RMS: f32 = 0.6;
+
+/// Seconds for a thunder clap to fade to a third.
+const THUNDER_DECAY: f32 = 1.5;
+
+/// Average seconds between thunder claps at full [`RainSynth::thunder`].
+const THUNDER_INTERVAL: f32 = 10.0;
+
+/// [`BackgroundConfig::point_density`] of the drops at full intensity.
+const MAX_DROP_DENSITY: f32 = 40.0;
+
+/// Rain and thunder noise.
+///
+/// Every block of samples is built in the frequency domain, the magnitude of every bin from
+/// the shape of rain and thunder and its phase at random, then turned into samples by an
+/// inverse FFT. Blocks are cross-faded with sine windows, which keeps the loudness of the
+/// noise constant across the fades.
+pub struct RainSynth {
+    /// Loudness from 0.0 to 1.0.
+    pub volume: f32,
+
+    /// How hard it rains, from 0.0 to 1.0.
+    pub intensity: f32,
+
+    /// How often it thunders, from 0.0 to 1.0.
+    pub thunder: f32,
+
+    sample_rate: f32,
+
+    /// Loudness of the current thunder clap, fading out.
+    rumble: f32,
+
+    /// Joins the blocks, fading each into the next.
+    overlap_add: OverlapAdd,
+
+    /// Finished samples, handed out from `position` on.
+    ready: Vec<f32>,
+    position: usize,
+
+    /// Magnitudes of rain and thunder at full strength for every bin, at the sample rate.
+    shapes: Vec<(f32, f32)>,
+
+    spectrum: Vec<Complex>,
+    block: Vec<f32>,
+    rng: ChaCha20Rng,
+}
+
+impl Default for RainSynth {
+    fn default() -> Self {
+        Self::with_seed(rand::random())
+    }
+}
+
+impl RainSynth {
+    pub fn with_seed(seed: u64) -> Self {
+        let mut synth = Self {
+            volume: 0.5,
+            intensity: 0.5,
+            thunder: 0.3,
+            sample_rate: 48_000.0,
+            rumble: 0.0,
+            overlap_add: OverlapAdd::new(Window::Sine.coefficients(BLOCK_SIZE), HOP_SIZE),
+            ready: Vec::new(),
+            position: 0,
+            shapes: Vec::new(),
+            spectrum: vec![Complex::ZERO; BLOCK_SIZE],
+            block: vec![0.0; BLOCK_SIZE],
+            rng: ChaCha20Rng::seed_from_u64(seed),
+        };
+        synth.set_sample_rate(synth.sample_rate);
+        synth
+    }
+
+    /// Start a thunder clap right away.
+    pub fn thunder_clap(&mut self) {
+        self.rumble = self.rng.gen_range(0.5..=1.0);
+    }
+
+    /// Add [`HOP_SIZE`] samples to `ready`.
+    fn generate_hop(&mut self) {
+        let hop_seconds = HOP_SIZE as f32 / self.sample_rate;
+        self.rumble *= (-hop_seconds / THUNDER_DECAY).exp();
+        if self.rng.gen::<f32>() < self.thunder * hop_seconds / THUNDER_INTERVAL {
+            self.thunder_clap();
+        }
+        // The rain patters: every block is a little louder or quieter than the last.
+        let rain = self.intensity * self.rng.gen_range(0.7..1.3);
+
+        self.spectrum[0] = Complex::ZERO;
+        self.spectrum[BLOCK_SIZE / 2] = Complex::ZERO;
+        for k in 1..BLOCK_SIZE / 2 {
+            let (rain_shape, thunder_shape) = self.shapes[k];
+            let magnitude = rain * rain_shape + self.rumble * thunder_shape;
+            let phase = self.rng.gen_range(0.0..std::f32::consts::TAU);
+            let bin = Complex::from_polar(magnitude, phase);
+            // Mirrored as the conjugate, so the samples come out real.
+            self.spectrum[k] = bin;
+            self.spectrum[BLOCK_SIZE - k] = bin.conj();
+        }
+        dsp::ifft(&mut self.spectrum);
+
+        for (sample, bin) in self.block.iter_mut().zip(&self.spectrum) {
+            *sample = bin.re;
+        }
+        self.overlap_add.add(&self.block, &mut self.ready);
+        for sample in &mut self.ready {
+            *sample = (*sample * self.volume).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+/// Magnitude of the rain at `frequency`, a bell over the octaves around [`RAIN_CENTER`].
+fn rain_shape(frequency: f32) -> f32 {
+    let octaves = (frequency / RAIN_CENTER).log2() / RAIN_WIDTH;
+    (-0.5 * octaves * octaves).exp()
+}
+
+/// Magnitude of thunder at `frequency`, a low pass.
+fn thunder_shape(frequency: f32) -> f32 {
+    1.0 / (1.0 + (frequency / THUNDER_CUTOFF).powi(2))
+}
+
+/// The magnitudes of `shape` for every bin below Nyquist at `sample_rate`, scaled so the noise
+/// they make comes out with an RMS of `rms`.
+fn normalized_shape(shape: fn(f32) -> f32, sample_rate: f32, rms: f32) -> Vec<f32> {
+    let magnitudes: Vec<f32> = (0..BLOCK_SIZE / 2)
+        .map(|k| shape(k as f32 * sample_rate / BLOCK_SIZE as f32))
+        .collect();
+    // Every bin but DC is a sinusoid of amplitude 2·|X|/N, with half its amplitude squared
+    // as power.
+    let power =
+        magnitudes[1..].iter().map(|m| 2.0 * m * m).sum::<f32>() / (BLOCK_SIZE * BLOCK_SIZE) as f32;
+    let gain = rms / power.sqrt();
+    magnitudes.into_iter().map(|m| m * gain).collect()
+}
+
+impl Synth for RainSynth {
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        let rain = normalized_shape(rain_shape, sample_rate, RAIN_RMS);
+        let thunder = normalized_shape(thunder_shape, sample_rate, THUNDER_RMS);
+        self.shapes = rain.into_iter().zip(thunder).collect();
+    }
+
+    fn fill(&mut self, out: &mut [f32]) {
+        for sample in out {
+            if self.position == self.ready.len() {
+                self.ready.clear();
+                self.position = 0;
+                self.generate_hop();
+            }
+            *sample = self.ready[self.position];
+            self.position += 1;
+        }
+    }
+}
+
+/// The window controlling the rain: its sound, and whether it falls on the background.
+pub struct RainWindow {
+    pub open: bool,
+
+    synth: Arc<Mutex<RainSynth>>,
+
+    /// Playing while set.
+    output: Option<AudioOutput>,
+
+    /// Why the sound couldn't start.
+    error: Option<String>,
+
+    /// What the background looked like before it started raining, back when it stops.
+    config_before: Option<BackgroundConfig>,
+}
+
+impl Default for RainWindow {
+    fn default() -> Self {
+        Self {
+            open: false,
+            synth: Arc::new(Mutex::new(RainSynth::default())),
+            output: None,
+            error: None,
+            config_before: None,
+        }
+    }
+}
+
+impl RainWindow {
+    pub fn show(&mut self, ctx: &egui::Context, background: &mut Background) {
+        let mut open = self.open;
+        egui::Window::new("Rain")
+            .open(&mut open)
+            .auto_sized()
+            .show(ctx, |ui| self.ui(ui, background));
+        if !open {
+            self.close(background);
+        }
+    }
+
+    /// Close the window, which ends the scene: the sound stops and the background is put back.
+    pub fn close(&mut self, background: &mut Background) {
+        self.open = false;
+        self.output = None;
+        self.stop_raining(background);
+    }
+
+    /// The background configuration to restore once it stops raining, `None` when it isn't.
+    pub fn config_before(&self) -> Option<&BackgroundConfig> {
+        self.config_before.as_ref()
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, background: &mut Background) {
+        let Ok(mut synth) = self.synth.lock() else {
+            return;
+        };
+        let mut raining = self.config_before.is_some();
+
+        let changed = egui::Grid::new("rain").num_columns(2).show(ui, |ui| {
+            ui.label("Intensity");
+            let intensity = ui.add(egui::Slider::new(&mut synth.intensity, 0.0..=1.0));
+            ui.end_row();
+
+            ui.label("Thunder");
+            ui.horizontal(|ui| {
+                ui.add(egui::Slider::new(&mut synth.thunder, 0.0..=1.0));
+                if ui.button("Now").clicked() {
+                    synth.thunder_clap();
+                }
+            });
+            ui.end_row();
+
+            ui.label("Volume");
+            ui.add(egui::Slider::new(&mut synth.volume, 0.0..=1.0));
+            ui.end_row();
+
+            ui.label("Background");
+            let toggled = ui.checkbox(&mut raining, "Rain on the background");
+            ui.end_row();
+
+            toggled.changed() || (raining && intensity.changed())
+        });
+        let intensity = synth.intensity;
+        drop(synth);
+        if changed.inner {
+            self.rain_on(background, raining.then_some(intensity));
+        }
+
+        ui.horizontal(|ui| {
+            if self.output.is_some() {
+                if ui.button("⏹ Stop sound").clicked() {
+                    self.output = None;
+                }
+            } else if ui.button("▶ Play sound").clicked() {
+                match AudioOutput::start(self.synth.clone()) {
+                    Ok(output) => {
+                        self.output = Some(output);
+                        self.error = None;
+                    }
+                    Err(err) => self.error = Some(err),
+                }
+            }
+        });
+        if let Some(err) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+    }
+
+    /// Let it rain on the background at `intensity`, or stop for `None`.
+    fn rain_on(&mut self, background: &mut Background, intensity: Option<f32>) {
+        let Some(intensity) = intensity else {
+            self.stop_raining(background);
+            return;
+        };
+        if self.config_before.is_none() {
+            self.config_before = Some(background.config.clone());
+        }
+        let mut config = BackgroundConfig::rain();
+        config.point_density = intensity * MAX_DROP_DENSITY;
+        background.set_config(config);
+    }
+
+    fn stop_raining(&mut self, background: &mut Background) {
+        if let Some(config) = self.config_before.take() {
+            background.set_config(config);
+        }
+    }
+}
+
+/// Average power spectrum of `samples`, over Hann windowed frames of `frame_size` samples.
+#[cfg(test)]
+fn power_spectrum(samples: &[f32], frame_size: usize) -> Vec<f32> {
+    let window = Window::Hann.coefficients(frame_size);
+    let mut power = vec![0.0; frame_size / 2];
+    let mut frames = 0;
+    for frame in samples.chunks_exact(frame_size) {
+        let mut bins: Vec<Complex> = frame
+            .iter()
+            .zip(&window)
+            .map(|(&x, &w)| Complex::new(x * w, 0.0))
+            .collect();
+        dsp::fft(&mut bins);
+        for (power, bin) in power.iter_mut().zip(&bins) {
+            *power += bin.norm_sqr();
+        }
+        frames += 1;
+    }
+    for power in &mut power {
+        *power /= frames as f32;
+    }
+    power
+}
+
+#[test]
+fn test_rain_spectrum() {
+    const SAMPLE_RATE: f32 = 48_000.0;
+    const FRAME_SIZE: usize = 4096;
+    let render = |intensity: f32, thunder: bool, volume: f32| {
+        let mut synth = RainSynth::with_seed(1);
+        synth.set_sample_rate(SAMPLE_RATE);
+        (synth.intensity, synth.thunder, synth.volume) = (intensity, 0.0, volume);
+        if thunder {
+            synth.thunder_clap();
+        }
+        let mut samples = vec![0.0; 48_000];
+        // Odd sizes, the way audio callbacks ask.
+        for chunk in samples.chunks_mut(1000) {
+            synth.fill(chunk);
+        }
+        samples
+    };
+    // Share of the power between `low` and `high` Hz.
+    let band = |samples: &[f32], low: f32, high: f32| {
+        let power = power_spectrum(samples, FRAME_SIZE);
+        let total: f32 = power.iter().sum();
+        let hz = SAMPLE_RATE / FRAME_SIZE as f32;
+        let start = (low / hz) as usize;
+        let end = ((high / hz) as usize).min(power.len());
+        power[start..end].iter().sum::<f32>() / total
+    };
+    let rms = |samples: &[f32]| {
+        (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+    };
+
+    // Rain hisses in the kilohertz range, with nothing in the bass.
+    let rain = render(1.0, false, 1.0);
+    assert!(band(&rain, 1000.0, 10_000.0) > 0.8);
+    assert!(band(&rain, 0.0, 200.0) < 0.01);
+    assert!(rain.iter().all(|x| (-1.0..=1.0).contains(x)));
+
+    // Thunder rumbles below a few hundred Hz.
+    let thunder = render(0.0, true, 1.0);
+    assert!(band(&thunder, 0.0, 300.0) > 0.9);
+
+    // Louder when it rains harder, silent without volume.
+    let drizzle = render(0.25, false, 1.0);
+    let ratio = rms(&rain) / rms(&drizzle);
+    assert!((3.0..5.0).contains(&ratio), "{ratio}");
+    assert!(render(1.0, true, 0.0).iter().all(|&x| x == 0.0));
+}
+
+#[test]
+fn test_closing_stops_the_rain() {
+    let mut background = Background::with_seed(0);
+    let before = background.config.clone();
+    let mut window = RainWindow {
+        open: true,
+        ..RainWindow::default()
+    };
+    window.rain_on(&mut background, Some(0.5));
+    assert_eq!(background.config.mode, BackgroundConfig::rain().mode);
+    assert_eq!(window.config_before(), Some(&before));
+
+    window.close(&mut background);
+    assert!(!window.open && window.output.is_none());
+    assert_eq!(window.config_before(), None);
+    assert_eq!(background.config, before);
+}